@@ -0,0 +1,67 @@
+use anyhow::Result;
+use clap::Parser;
+use manager_interface::mock_server::{self, MockConfig};
+use manager_interface::replay_server::{self, Transcript};
+use manager_interface::transport::Listener;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// In-memory theater server for developing and testing manager-interface
+/// without a real theater installation
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Address to listen on, host:port or unix:/path/to.sock
+    #[arg(long, default_value = "127.0.0.1:9000")]
+    address: String,
+
+    /// Milliseconds between the messages of a simulated operation
+    #[arg(long, default_value = "200")]
+    step_delay_ms: u64,
+
+    /// Make every simulated build fail
+    #[arg(long)]
+    fail_builds: bool,
+
+    /// Reject the protocol handshake like a server that predates it
+    #[arg(long)]
+    legacy: bool,
+
+    /// Answer with the frames of a session recorded with
+    /// `manager-interface --record` instead of simulating a server
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["fail_builds", "legacy"])]
+    replay: Option<PathBuf>,
+
+    /// Don't print the commands received
+    #[arg(long)]
+    quiet: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let listener = Listener::bind(&args.address).await?;
+    println!(
+        "Mock theater server listening on {}",
+        listener.local_addr()?
+    );
+
+    if let Some(path) = &args.replay {
+        let transcript = Transcript::load(path)?;
+        println!(
+            "Replaying {} connection(s) from {}",
+            transcript.connections(),
+            path.display()
+        );
+        return replay_server::serve(listener, transcript, !args.quiet).await;
+    }
+
+    let config = MockConfig {
+        step_delay: Duration::from_millis(args.step_delay_ms),
+        fail_builds: args.fail_builds,
+        log: !args.quiet,
+        legacy: args.legacy,
+    };
+    mock_server::serve(listener, config).await
+}
@@ -0,0 +1,213 @@
+use crate::client::ManagementClient;
+use crate::output;
+use crate::profile::{ActorSpec, Placeholders, Profile, StoreCreation};
+use crate::say;
+use crate::secrets;
+use crate::teardown::{self, StartedActors, TeardownReport};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde_json::json;
+use std::collections::HashMap;
+
+/// IDs of everything the bootstrap created or reused, keyed by profile name.
+#[derive(Debug, Default)]
+pub struct Bootstrapped {
+    pub stores: IndexMap<String, String>,
+    pub created_stores: Vec<String>,
+    pub actors: IndexMap<String, String>,
+}
+
+/// Creates or reuses every store in the profile, then starts its actors in
+/// dependency order, substituting the IDs of stores and earlier actors into
+/// each initial state. Every actor started is recorded in `started`.
+///
+/// Bootstrap is all or nothing: if any step fails, the actors started so far
+/// are stopped again in reverse order and a report of the cleanup is printed
+/// before the original error is returned.
+pub async fn run(
+    client: &mut ManagementClient,
+    profile: &Profile,
+    store_ids: &HashMap<String, String>,
+    new_store: bool,
+    started: &StartedActors,
+) -> Result<Bootstrapped> {
+    let mut result = Bootstrapped::default();
+    match start_all(client, profile, store_ids, new_store, started, &mut result).await {
+        Ok(()) => Ok(result),
+        // Nothing was started, so there is nothing to roll back
+        Err(e) if client.is_dry_run() => Err(e.context("Bootstrap failed")),
+        Err(e) => {
+            say!("\nBootstrap failed: {:#}", e);
+            say!("Rolling back...");
+            let report = started.stop_all(client).await;
+            print_rollback_report(&report, &result);
+            emit_rollback_report(&e, &report, &result);
+            Err(e.context("Bootstrap failed"))
+        }
+    }
+}
+
+fn print_rollback_report(report: &TeardownReport, result: &Bootstrapped) {
+    say!("\nRollback summary:");
+    if report.stopped.is_empty() && report.failed.is_empty() {
+        say!("  No actors had been started");
+    }
+    for (name, id) in &report.stopped {
+        say!("  stopped        {} ({})", name, id);
+    }
+    for (name, id, error) in &report.failed {
+        say!("  still running  {} ({}): {}", name, id, error);
+    }
+    // The management protocol has no way to delete a store
+    for name in &result.created_stores {
+        say!(
+            "  left in place  store {} ({}), created by this run",
+            name,
+            result.stores[name]
+        );
+    }
+}
+
+fn emit_rollback_report(error: &anyhow::Error, report: &TeardownReport, result: &Bootstrapped) {
+    let stopped: Vec<_> = report
+        .stopped
+        .iter()
+        .map(|(name, id)| json!({ "name": name, "id": id }))
+        .collect();
+    let still_running: Vec<_> = report
+        .failed
+        .iter()
+        .map(|(name, id, error)| json!({ "name": name, "id": id, "error": error }))
+        .collect();
+    let stores_left: Vec<_> = result
+        .created_stores
+        .iter()
+        .map(|name| json!({ "name": name, "id": result.stores[name] }))
+        .collect();
+    output::emit(
+        "bootstrap_failed",
+        json!({
+            "error": format!("{:#}", error),
+            "stopped": stopped,
+            "still_running": still_running,
+            "stores_left": stores_left,
+        }),
+    );
+}
+
+async fn start_all(
+    client: &mut ManagementClient,
+    profile: &Profile,
+    store_ids: &HashMap<String, String>,
+    new_store: bool,
+    started: &StartedActors,
+    result: &mut Bootstrapped,
+) -> Result<()> {
+    let mut placeholders = Placeholders::default();
+
+    // Resolve secrets before touching the server so a missing key fails fast.
+    // A dry run only plans, so it neither reads nor runs their sources.
+    for name in profile.referenced_secrets()? {
+        if client.is_dry_run() {
+            placeholders.set(format!("secrets.{}", name), secrets::MASK);
+            continue;
+        }
+        let value = profile.secrets[name]
+            .resolve()
+            .await
+            .with_context(|| format!("Could not resolve secret `{}`", name))?;
+        client.redactor().add(&value);
+        placeholders.set(format!("secrets.{}", name), value);
+    }
+
+    for (name, spec) in &profile.stores {
+        let create = match (spec.create, store_ids.get(name)) {
+            (StoreCreation::OnNewStore, _) if new_store => true,
+            (_, Some(_)) => false,
+            (StoreCreation::IfMissing, None) => true,
+            (StoreCreation::OnNewStore, None) => {
+                return Err(anyhow::anyhow!(
+                    "Store `{}` needs an ID: pass --new-store or --store {}=<ID>",
+                    name,
+                    name
+                ))
+            }
+        };
+
+        let id = if create {
+            teardown::check_shutdown()?;
+            say!("Creating new {} store...", name);
+            let id = create_store(client, name).await?;
+            result.created_stores.push(name.clone());
+            id
+        } else {
+            store_ids[name].clone()
+        };
+        placeholders.set(format!("stores.{}", name), &id);
+        result.stores.insert(name.clone(), id);
+    }
+
+    // Actors left out because of `when_created`, and everything that
+    // depends on them
+    let mut skipped: Vec<&str> = Vec::new();
+    for name in profile.start_order()? {
+        let spec = &profile.actors[name];
+        let reason = match &spec.when_created {
+            Some(store) if !result.created_stores.contains(store) => {
+                Some(format!("store {} was not created", store))
+            }
+            _ => profile
+                .dependencies(name)?
+                .into_iter()
+                .find(|dependency| skipped.contains(&dependency.as_str()))
+                .map(|dependency| format!("it depends on {}, which was skipped", dependency)),
+        };
+        if let Some(reason) = reason {
+            if name == profile.repl {
+                anyhow::bail!("Cannot start REPL actor {}: {}", name, reason);
+            }
+            say!("Skipping {} ({})", name, reason);
+            skipped.push(name);
+            continue;
+        }
+
+        // Once shutdown has begun nothing new is started, and a readiness
+        // wait is cut short; the actors started so far are rolled back
+        teardown::check_shutdown()?;
+        say!("Starting {}...", name);
+        let id = start_actor(client, name, spec, &placeholders).await?;
+        started.push(name, &id);
+        let probe = profile.readiness(name);
+        tokio::select! {
+            ready = probe.wait_ready(client, name, &id) => ready?,
+            signal = teardown::shutdown_requested() => anyhow::bail!("{}", signal),
+        }
+        placeholders.set(format!("actors.{}.id", name), &id);
+        result.actors.insert(name.to_string(), id);
+    }
+
+    Ok(())
+}
+
+async fn create_store(client: &mut ManagementClient, name: &str) -> Result<String> {
+    let store_id = client.new_store().await?;
+    say!("Created new store with ID: {}", store_id);
+    output::emit("store_created", json!({ "name": name, "id": store_id }));
+    Ok(store_id)
+}
+
+async fn start_actor(
+    client: &mut ManagementClient,
+    name: &str,
+    spec: &ActorSpec,
+    placeholders: &Placeholders,
+) -> Result<String> {
+    let initial_state = spec.render_initial_state(name, placeholders)?;
+    let id = client
+        .start_actor(spec.manifest_str(), initial_state)
+        .await
+        .with_context(|| format!("Could not start {}", name))?;
+    say!("Started {} with ID: {}", name, id);
+    output::emit("actor_started", json!({ "name": name, "id": id }));
+    Ok(id)
+}
@@ -0,0 +1,697 @@
+use crate::output;
+use crate::protocol::*;
+use crate::recording;
+use crate::say;
+use crate::secrets::Redactor;
+use crate::transport::{FramedStream, Transport};
+use anyhow::Context;
+use anyhow::Result;
+use bytes::Bytes;
+use futures::sink::SinkExt;
+use futures::stream::StreamExt;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Error reported by the theater server in a `ManagementResponse::Error`.
+#[derive(Debug, Clone)]
+pub struct ServerError {
+    pub message: String,
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Server error: {}", self.message)
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+/// The server closed the connection before answering a request.
+#[derive(Debug, Clone)]
+pub struct ConnectionClosed;
+
+impl fmt::Display for ConnectionClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Connection closed while waiting for a response")
+    }
+}
+
+impl std::error::Error for ConnectionClosed {}
+
+/// How long to wait for a server to answer the handshake before assuming it
+/// predates it.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for a server to stop an actor.
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What each server said in its handshake, so reconnecting to it does not
+/// repeat the handshake, or its timeout for a legacy server. Keyed by
+/// whether the connection uses TLS as well as the address, since a plain and
+/// a TLS listener may be different servers behind the same address.
+static KNOWN_SERVERS: Mutex<BTreeMap<(bool, String), Capabilities>> = Mutex::new(BTreeMap::new());
+
+/// What a server said it supports in the handshake.
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    /// `None` for servers that predate the handshake.
+    pub protocol_version: Option<u32>,
+    pub commands: BTreeSet<String>,
+}
+
+impl Capabilities {
+    /// A server that predates the handshake and only knows the commands that
+    /// existed before it.
+    pub fn legacy() -> Self {
+        Self {
+            protocol_version: None,
+            commands: LEGACY_COMMANDS.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    /// A server that speaks exactly this client's protocol.
+    pub fn current() -> Self {
+        Self {
+            protocol_version: Some(PROTOCOL_VERSION),
+            commands: ManagementCommand::NAMES
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+        }
+    }
+
+    pub fn supports(&self, command: &str) -> bool {
+        command == "Handshake" || self.commands.contains(command)
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.protocol_version {
+            Some(version) => write!(f, "protocol version {}", version),
+            None => write!(f, "legacy protocol, no handshake"),
+        }
+    }
+}
+
+/// Typed client for the theater management protocol.
+///
+/// Each request waits for the response variant that answers it. Frames that
+/// arrive in the meantime but answer nothing, such as `ChannelMessage`, are
+/// queued and handed out by [`ManagementClient::next_unsolicited`].
+///
+/// The first connection to an address performs a handshake that learns which
+/// commands the server supports; sending any other command fails without
+/// reaching the server. Later connections to the address over the same kind
+/// of transport reuse what it said.
+pub struct ManagementClient {
+    connection: Connection,
+    unsolicited: VecDeque<ManagementResponse>,
+    redactor: Redactor,
+    capabilities: Capabilities,
+    /// Identifies this connection's frames in a recording.
+    connection_id: u64,
+    /// Replies still owed to requests that were given up on, dropped when
+    /// they arrive so a later request does not take them for its own.
+    abandoned: Vec<CommandKey>,
+}
+
+enum Connection {
+    Framed(FramedStream),
+    /// Prints each command instead of sending it; see
+    /// [`ManagementClient::dry_run`].
+    DryRun {
+        sent: u32,
+    },
+}
+
+impl ManagementClient {
+    pub async fn connect(address: &str, transport: &Transport) -> Result<Self> {
+        let key = (transport.is_tls(), address.to_string());
+        let known = KNOWN_SERVERS.lock().unwrap().get(&key).cloned();
+        if let Some(capabilities) = known {
+            return Self::open(address, transport, capabilities).await;
+        }
+
+        let mut client = Self::open(address, transport, Capabilities::legacy()).await?;
+        let handshake = client
+            .handshake()
+            .await
+            .with_context(|| format!("Handshake with {} failed", address))?;
+        match handshake {
+            Some(capabilities) => client.capabilities = capabilities,
+            // Some servers that predate the handshake hang up on commands
+            // they don't know
+            None => client = Self::open(address, transport, Capabilities::legacy()).await?,
+        }
+        KNOWN_SERVERS
+            .lock()
+            .unwrap()
+            .insert(key, client.capabilities.clone());
+        Ok(client)
+    }
+
+    async fn open(
+        address: &str,
+        transport: &Transport,
+        capabilities: Capabilities,
+    ) -> Result<Self> {
+        Ok(Self {
+            connection: Connection::Framed(transport.connect(address).await?),
+            unsolicited: VecDeque::new(),
+            redactor: Redactor::global(),
+            capabilities,
+            connection_id: recording::connected(address),
+            abandoned: Vec::new(),
+        })
+    }
+
+    /// A client that never connects: every command is printed, with its
+    /// payloads decoded and secrets redacted, and answered with a plausible
+    /// placeholder response so callers proceed as they would for real.
+    pub fn dry_run() -> Self {
+        Self {
+            connection: Connection::DryRun { sent: 0 },
+            unsolicited: VecDeque::new(),
+            redactor: Redactor::global(),
+            capabilities: Capabilities::current(),
+            connection_id: 0,
+            abandoned: Vec::new(),
+        }
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        matches!(self.connection, Connection::DryRun { .. })
+    }
+
+    /// Secrets registered here are masked in server error messages, which
+    /// may echo back parts of an initial state, and in recordings. Every
+    /// client in the process shares them.
+    pub fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
+
+    /// Tells the server our protocol version and learns what it supports.
+    /// Servers that predate the handshake answer with an error, not at all,
+    /// or by closing the connection, and are assumed to support the legacy
+    /// commands. `None` means the connection is gone and must be reopened.
+    /// Anything else going wrong, such as an I/O error or a `ServerInfo`
+    /// that does not decode, is a real failure and returned as such.
+    async fn handshake(&mut self) -> Result<Option<Capabilities>> {
+        let command = ManagementCommand::Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            client: format!("manager-interface {}", env!("CARGO_PKG_VERSION")),
+        };
+        let reply = tokio::time::timeout(
+            HANDSHAKE_TIMEOUT,
+            self.request(command, |r| {
+                matches!(r, ManagementResponse::ServerInfo { .. })
+            }),
+        )
+        .await;
+
+        match reply {
+            Ok(Ok(ManagementResponse::ServerInfo {
+                protocol_version,
+                min_protocol_version,
+                commands,
+            })) => {
+                if PROTOCOL_VERSION < min_protocol_version {
+                    anyhow::bail!(
+                        "Server speaks protocol version {} and needs clients of at least \
+                         version {}, but this client speaks version {}; upgrade manager-interface",
+                        protocol_version,
+                        min_protocol_version,
+                        PROTOCOL_VERSION
+                    );
+                }
+                Ok(Some(Capabilities {
+                    protocol_version: Some(protocol_version),
+                    commands: commands.into_iter().collect(),
+                }))
+            }
+            Ok(Ok(_)) => unreachable!(),
+            Ok(Err(e)) if e.is::<ServerError>() => Ok(Some(Capabilities::legacy())),
+            Ok(Err(e)) if e.is::<ConnectionClosed>() => Ok(None),
+            Ok(Err(e)) => Err(e),
+            Err(_) => {
+                // A slow server may still answer, after the next command
+                self.abandoned.push(CommandKey {
+                    name: "Handshake",
+                    subject: None,
+                });
+                Ok(Some(Capabilities::legacy()))
+            }
+        }
+    }
+
+    pub async fn new_store(&mut self) -> Result<String> {
+        let response = self
+            .request(ManagementCommand::NewStore {}, |r| {
+                matches!(r, ManagementResponse::StoreCreated { .. })
+            })
+            .await?;
+        match response {
+            ManagementResponse::StoreCreated { store_id } => Ok(store_id),
+            _ => unreachable!(),
+        }
+    }
+
+    pub async fn start_actor(
+        &mut self,
+        manifest: String,
+        initial_state: Option<Vec<u8>>,
+    ) -> Result<String> {
+        let command = ManagementCommand::StartActor {
+            manifest,
+            initial_state,
+        };
+        let response = self
+            .request(command, |r| {
+                matches!(r, ManagementResponse::ActorStarted { .. })
+            })
+            .await?;
+        match response {
+            ManagementResponse::ActorStarted { id } => Ok(id),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Stops the actor, giving up after [`STOP_TIMEOUT`] so rollback and
+    /// teardown cannot hang on a server that never answers.
+    pub async fn stop_actor(&mut self, id: &str) -> Result<()> {
+        let command = ManagementCommand::StopActor { id: id.to_string() };
+        self.request_within(
+            command,
+            |r| matches!(r, ManagementResponse::ActorStopped { .. }),
+            STOP_TIMEOUT,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Sends `data` to the actor and returns its reply.
+    pub async fn request_actor_message(&mut self, id: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+        let command = ManagementCommand::RequestActorMessage {
+            id: id.to_string(),
+            data,
+        };
+        let response = self
+            .request(command, |r| {
+                matches!(r, ManagementResponse::RequestedMessage { id: from, .. } if from == id)
+            })
+            .await?;
+        match response {
+            ManagementResponse::RequestedMessage { message, .. } => Ok(message),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Like [`ManagementClient::request_actor_message`], but gives up after
+    /// `timeout`. A reply that arrives after that is discarded.
+    pub async fn request_actor_message_within(
+        &mut self,
+        id: &str,
+        data: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<Vec<u8>> {
+        let command = ManagementCommand::RequestActorMessage {
+            id: id.to_string(),
+            data,
+        };
+        let response = self
+            .request_within(
+                command,
+                |r| matches!(r, ManagementResponse::RequestedMessage { id: from, .. } if from == id),
+                timeout,
+            )
+            .await?;
+        match response {
+            ManagementResponse::RequestedMessage { message, .. } => Ok(message),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Opens a channel and returns its ID.
+    pub async fn open_channel(
+        &mut self,
+        actor_id: ChannelParticipant,
+        initial_message: Vec<u8>,
+    ) -> Result<String> {
+        let command = ManagementCommand::OpenChannel {
+            actor_id,
+            initial_message,
+        };
+        let response = self
+            .request(command, |r| {
+                matches!(r, ManagementResponse::ChannelOpened { .. })
+            })
+            .await?;
+        match response {
+            ManagementResponse::ChannelOpened { channel_id, .. } => Ok(channel_id),
+            _ => unreachable!(),
+        }
+    }
+
+    pub async fn send_on_channel(&mut self, channel_id: &str, message: Vec<u8>) -> Result<()> {
+        let command = ManagementCommand::SendOnChannel {
+            channel_id: channel_id.to_string(),
+            message,
+        };
+        self.request(command, |r| {
+            matches!(r, ManagementResponse::MessageSent { .. })
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub async fn close_channel(&mut self, channel_id: &str) -> Result<()> {
+        let command = ManagementCommand::CloseChannel {
+            channel_id: channel_id.to_string(),
+        };
+        self.request(command, |r| {
+            matches!(r, ManagementResponse::ChannelClosed { .. })
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub async fn restart_actor(&mut self, id: &str) -> Result<()> {
+        self.execute(ManagementCommand::RestartActor { id: id.to_string() })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_actors(&mut self) -> Result<Vec<String>> {
+        match self.execute(ManagementCommand::ListActors {}).await? {
+            ManagementResponse::ActorList { actors } => Ok(actors),
+            _ => unreachable!(),
+        }
+    }
+
+    pub async fn get_actor_state(&mut self, id: &str) -> Result<Option<Vec<u8>>> {
+        let command = ManagementCommand::GetActorState { id: id.to_string() };
+        match self.execute(command).await? {
+            ManagementResponse::ActorState { state, .. } => Ok(state),
+            _ => unreachable!(),
+        }
+    }
+
+    pub async fn get_actor_events(&mut self, id: &str) -> Result<Vec<serde_json::Value>> {
+        let command = ManagementCommand::GetActorEvents { id: id.to_string() };
+        match self.execute(command).await? {
+            ManagementResponse::ActorEvents { events, .. } => Ok(events),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Sends `data` to the actor without waiting for it to be handled.
+    pub async fn send_actor_message(&mut self, id: &str, data: Vec<u8>) -> Result<()> {
+        let command = ManagementCommand::SendActorMessage {
+            id: id.to_string(),
+            data,
+        };
+        self.execute(command).await?;
+        Ok(())
+    }
+
+    /// Subscribes to the actor's events, which then arrive as unsolicited
+    /// `ActorEvent` frames. Returns the subscription ID.
+    pub async fn subscribe_to_actor(&mut self, id: &str) -> Result<String> {
+        let command = ManagementCommand::SubscribeToActor { id: id.to_string() };
+        match self.execute(command).await? {
+            ManagementResponse::Subscribed {
+                subscription_id, ..
+            } => Ok(subscription_id),
+            _ => unreachable!(),
+        }
+    }
+
+    pub async fn unsubscribe_from_actor(&mut self, id: &str, subscription_id: &str) -> Result<()> {
+        let command = ManagementCommand::UnsubscribeFromActor {
+            id: id.to_string(),
+            subscription_id: subscription_id.to_string(),
+        };
+        self.execute(command).await?;
+        Ok(())
+    }
+
+    /// Sends any command and returns the response that answers it.
+    pub async fn execute(&mut self, command: ManagementCommand) -> Result<ManagementResponse> {
+        let answered = command_key(&command);
+        self.request(command, move |r| answers(&answered, r)).await
+    }
+
+    /// Next frame that did not answer a request, or `None` once the server
+    /// closes the connection. Frames that cannot be decoded are skipped.
+    /// Cancel safe.
+    pub async fn next_unsolicited(&mut self) -> Result<Option<ManagementResponse>> {
+        if let Some(response) = self.unsolicited.pop_front() {
+            return Ok(Some(response));
+        }
+        let framed = match &mut self.connection {
+            Connection::Framed(framed) => framed,
+            Connection::DryRun { .. } => return Ok(None),
+        };
+        loop {
+            match framed.next().await {
+                Some(Ok(bytes)) => {
+                    if let Ok(response) = ManagementResponse::decode(&bytes) {
+                        recording::received(self.connection_id, &self.redactor, &response);
+                        if !discard_abandoned(&mut self.abandoned, &response) {
+                            return Ok(Some(response));
+                        }
+                    }
+                }
+                Some(Err(e)) => return Err(e.into()),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Sends `command` and waits for the first response accepted by
+    /// `is_reply`, queueing anything else that arrives first. An `Error`
+    /// response is returned as a [`ServerError`].
+    async fn request(
+        &mut self,
+        command: ManagementCommand,
+        is_reply: impl Fn(&ManagementResponse) -> bool,
+    ) -> Result<ManagementResponse> {
+        if !self.capabilities.supports(command.name()) {
+            anyhow::bail!(
+                "The server does not support {} ({})",
+                command.name(),
+                self.capabilities
+            );
+        }
+        let framed = match &mut self.connection {
+            Connection::Framed(framed) => framed,
+            Connection::DryRun { sent } => {
+                *sent += 1;
+                let json = self.redactor.redact_value(&command.to_display_json());
+                say!(
+                    "[dry-run #{}] {}",
+                    sent,
+                    serde_json::to_string_pretty(&json)?
+                );
+                output::emit(
+                    "planned_command",
+                    serde_json::json!({ "sequence": sent, "command": json }),
+                );
+                return Ok(dry_run_reply(command, *sent));
+            }
+        };
+        framed
+            .send(Bytes::from(serde_json::to_vec(&command)?))
+            .await?;
+        recording::sent(self.connection_id, &self.redactor, &command);
+
+        loop {
+            match self.read().await? {
+                Some(ManagementResponse::Error { message }) => {
+                    let message = self.redactor.redact(&message);
+                    return Err(ServerError { message }.into());
+                }
+                Some(response) if is_reply(&response) => {
+                    // The server answers in order, so whatever was given up
+                    // on before this request will never be answered now
+                    self.abandoned.clear();
+                    return Ok(response);
+                }
+                Some(response) => self.unsolicited.push_back(response),
+                None => return Err(ConnectionClosed.into()),
+            }
+        }
+    }
+
+    /// Like [`ManagementClient::request`], but gives up after `timeout`,
+    /// remembering that the reply is still owed so it is dropped on arrival.
+    async fn request_within(
+        &mut self,
+        command: ManagementCommand,
+        is_reply: impl Fn(&ManagementResponse) -> bool,
+        timeout: Duration,
+    ) -> Result<ManagementResponse> {
+        let key = command_key(&command);
+        match tokio::time::timeout(timeout, self.request(command, is_reply)).await {
+            Ok(result) => result,
+            Err(_) => {
+                self.abandoned.push(key);
+                anyhow::bail!("no reply within {:?}", timeout)
+            }
+        }
+    }
+
+    async fn read(&mut self) -> Result<Option<ManagementResponse>> {
+        let framed = match &mut self.connection {
+            Connection::Framed(framed) => framed,
+            Connection::DryRun { .. } => return Ok(None),
+        };
+        loop {
+            match framed.next().await {
+                Some(Ok(bytes)) => {
+                    let response = ManagementResponse::decode(&bytes)?;
+                    recording::received(self.connection_id, &self.redactor, &response);
+                    if !discard_abandoned(&mut self.abandoned, &response) {
+                        return Ok(Some(response));
+                    }
+                }
+                Some(Err(e)) => return Err(e.into()),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Whether `response` is the late reply to an abandoned request, which is
+/// then no longer owed. An `Error` names no command, but the server answers
+/// in order, so one arriving while replies are still owed answers the oldest
+/// abandoned request rather than anything sent after it. For the same
+/// reason, requests abandoned before the one answered never will be.
+fn discard_abandoned(abandoned: &mut Vec<CommandKey>, response: &ManagementResponse) -> bool {
+    let owed = match response {
+        ManagementResponse::Error { .. } if !abandoned.is_empty() => Some(0),
+        _ => abandoned.iter().position(|key| answers(key, response)),
+    };
+    match owed {
+        Some(index) => {
+            abandoned.drain(..=index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// What identifies the reply to a command: the variant name and, where the
+/// reply carries one, the actor or channel it concerns.
+struct CommandKey {
+    name: &'static str,
+    subject: Option<String>,
+}
+
+fn command_key(command: &ManagementCommand) -> CommandKey {
+    let subject = match command {
+        ManagementCommand::StopActor { id }
+        | ManagementCommand::RequestActorMessage { id, .. }
+        | ManagementCommand::RestartActor { id }
+        | ManagementCommand::GetActorState { id }
+        | ManagementCommand::GetActorEvents { id }
+        | ManagementCommand::SendActorMessage { id, .. }
+        | ManagementCommand::SubscribeToActor { id }
+        | ManagementCommand::UnsubscribeFromActor { id, .. } => Some(id.clone()),
+        ManagementCommand::SendOnChannel { channel_id, .. }
+        | ManagementCommand::CloseChannel { channel_id } => Some(channel_id.clone()),
+        _ => None,
+    };
+    CommandKey {
+        name: command.name(),
+        subject,
+    }
+}
+
+fn answers(key: &CommandKey, response: &ManagementResponse) -> bool {
+    let (name, subject) = match response {
+        ManagementResponse::ServerInfo { .. } => ("Handshake", None),
+        ManagementResponse::StoreCreated { .. } => ("NewStore", None),
+        ManagementResponse::ActorStarted { .. } => ("StartActor", None),
+        ManagementResponse::ActorStopped { id } => ("StopActor", Some(id)),
+        ManagementResponse::RequestedMessage { id, .. } => ("RequestActorMessage", Some(id)),
+        ManagementResponse::ChannelOpened { .. } => ("OpenChannel", None),
+        ManagementResponse::MessageSent { channel_id } => ("SendOnChannel", Some(channel_id)),
+        ManagementResponse::ChannelClosed { channel_id } => ("CloseChannel", Some(channel_id)),
+        ManagementResponse::ActorRestarted { id } => ("RestartActor", Some(id)),
+        ManagementResponse::ActorList { .. } => ("ListActors", None),
+        ManagementResponse::ActorState { id, .. } => ("GetActorState", Some(id)),
+        ManagementResponse::ActorEvents { id, .. } => ("GetActorEvents", Some(id)),
+        ManagementResponse::SentMessage { id } => ("SendActorMessage", Some(id)),
+        ManagementResponse::Subscribed { id, .. } => ("SubscribeToActor", Some(id)),
+        ManagementResponse::Unsubscribed { id } => ("UnsubscribeFromActor", Some(id)),
+        ManagementResponse::ChannelMessage { .. }
+        | ManagementResponse::ActorEvent { .. }
+        | ManagementResponse::Error { .. }
+        | ManagementResponse::Unknown { .. } => return false,
+    };
+    name == key.name
+        && match (&key.subject, subject) {
+            (Some(expected), Some(actual)) => expected == actual,
+            _ => true,
+        }
+}
+
+/// The response a server would plausibly give, with placeholder IDs numbered
+/// after the command that produced them.
+fn dry_run_reply(command: ManagementCommand, sent: u32) -> ManagementResponse {
+    match command {
+        ManagementCommand::Handshake { .. } => ManagementResponse::ServerInfo {
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: PROTOCOL_VERSION,
+            commands: ManagementCommand::NAMES
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+        },
+        ManagementCommand::NewStore {} => ManagementResponse::StoreCreated {
+            store_id: format!("dry-run-store-{}", sent),
+        },
+        ManagementCommand::StartActor { .. } => ManagementResponse::ActorStarted {
+            id: format!("dry-run-actor-{}", sent),
+        },
+        ManagementCommand::StopActor { id } => ManagementResponse::ActorStopped { id },
+        ManagementCommand::RequestActorMessage { id, .. } => ManagementResponse::RequestedMessage {
+            id,
+            message: b"{}".to_vec(),
+        },
+        ManagementCommand::OpenChannel { actor_id, .. } => ManagementResponse::ChannelOpened {
+            channel_id: format!("dry-run-channel-{}", sent),
+            actor_id,
+        },
+        ManagementCommand::SendOnChannel { channel_id, .. } => {
+            ManagementResponse::MessageSent { channel_id }
+        }
+        ManagementCommand::CloseChannel { channel_id } => {
+            ManagementResponse::ChannelClosed { channel_id }
+        }
+        ManagementCommand::RestartActor { id } => ManagementResponse::ActorRestarted { id },
+        ManagementCommand::ListActors {} => ManagementResponse::ActorList { actors: vec![] },
+        ManagementCommand::GetActorState { id } => {
+            ManagementResponse::ActorState { id, state: None }
+        }
+        ManagementCommand::GetActorEvents { id } => {
+            ManagementResponse::ActorEvents { id, events: vec![] }
+        }
+        ManagementCommand::SendActorMessage { id, .. } => ManagementResponse::SentMessage { id },
+        ManagementCommand::SubscribeToActor { id } => ManagementResponse::Subscribed {
+            id,
+            subscription_id: format!("dry-run-subscription-{}", sent),
+        },
+        ManagementCommand::UnsubscribeFromActor { id, .. } => {
+            ManagementResponse::Unsubscribed { id }
+        }
+    }
+}
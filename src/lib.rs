@@ -0,0 +1,18 @@
+pub mod bootstrap;
+pub mod client;
+pub mod mock_server;
+pub mod oneshot;
+pub mod operations;
+pub mod output;
+pub mod profile;
+pub mod protocol;
+pub mod readiness;
+pub mod recording;
+pub mod repl;
+pub mod replay;
+pub mod replay_server;
+pub mod script;
+pub mod secrets;
+pub mod session;
+pub mod teardown;
+pub mod transport;
@@ -1,20 +1,30 @@
-mod protocol;
-mod repl;
-
-use anyhow::Result;
-use bytes::Bytes;
-use clap::Parser;
-use futures::sink::SinkExt;
-use futures::stream::StreamExt;
-use protocol::*;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use manager_interface::client::ManagementClient;
+use manager_interface::oneshot;
+use manager_interface::output::{self, Format};
+use manager_interface::profile::Profile;
+use manager_interface::protocol::FrontendCommand;
+use manager_interface::recording;
+use manager_interface::replay::{self, Filter, Pace};
+use manager_interface::script::Script;
+use manager_interface::secrets::SecretSource;
+use manager_interface::session::Session;
+use manager_interface::teardown::{self, StartedActors};
+use manager_interface::transport::{self, TlsOptions, Transport};
+use manager_interface::{bootstrap, repl, say};
 use serde_json::json;
-use std::env;
-use tokio::net::TcpStream;
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Create a new store
     #[arg(long)]
     new_store: bool,
@@ -27,270 +37,524 @@ struct Args {
     #[arg(long)]
     build_store_id: Option<String>,
 
-    /// Server address
-    #[arg(long, default_value = "127.0.0.1:9000")]
+    /// Use an existing ID for any store declared in the profile
+    #[arg(long = "store", value_name = "NAME=ID", value_parser = parse_store_id)]
+    stores: Vec<(String, String)>,
+
+    /// Server address, host:port or unix:/path/to.sock
+    #[arg(long, global = true, default_value = "127.0.0.1:9000")]
     address: String,
 
+    /// Bootstrap profile describing the actor manifests and initial states
+    #[arg(
+        long,
+        env = "MANAGER_INTERFACE_PROFILE",
+        default_value = "manager-interface.toml"
+    )]
+    profile: PathBuf,
+
+    /// Read a secret from env:VAR, file:PATH or command:PROGRAM ARGS,
+    /// overriding the profile's [secrets.NAME]
+    #[arg(long = "secret", value_name = "NAME=SOURCE", value_parser = parse_secret)]
+    secrets: Vec<(String, SecretSource)>,
+
+    /// File the store and actor IDs of this session are written to
+    #[arg(long, global = true, default_value = ".manager-session.json")]
+    session_file: PathBuf,
+
+    /// Reuse the stores and, if still alive, the actors of the last session
+    #[arg(long)]
+    resume: bool,
+
+    /// Print the management commands the bootstrap would send without
+    /// connecting to the server
+    #[arg(long, conflicts_with = "resume")]
+    dry_run: bool,
+
+    /// Leave the actors started by this session running on exit
+    #[arg(long)]
+    keep_running: bool,
+
+    /// Connect to the server over TLS
+    #[arg(long, global = true)]
+    tls: bool,
+
+    /// PEM file with the CA certificates to trust instead of the built-in roots
+    #[arg(long, global = true, value_name = "PEM", requires = "tls")]
+    tls_ca: Option<PathBuf>,
+
+    /// PEM client certificate to present to the server
+    #[arg(long, global = true, value_name = "PEM", requires_all = ["tls", "tls_key"])]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key of the client certificate
+    #[arg(long, global = true, value_name = "PEM", requires_all = ["tls", "tls_cert"])]
+    tls_key: Option<PathBuf>,
+
+    /// Name to verify the server certificate against instead of the host in
+    /// --address
+    #[arg(long, global = true, value_name = "NAME", requires = "tls")]
+    tls_server_name: Option<String>,
+
+    /// Run the REPL commands in this file, or stdin for `-`, instead of
+    /// reading them interactively; stdin is used too when it is not a
+    /// terminal
+    #[arg(long, global = true, value_name = "FILE")]
+    script: Option<PathBuf>,
+
+    /// Read REPL commands line by line as typed, even when stdin is not a
+    /// terminal
+    #[arg(long, global = true, conflicts_with = "script")]
+    interactive: bool,
+
+    /// Output format: text, or json/ndjson for one JSON object per line
+    /// with a timestamp, leaving progress text on stderr
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "text")]
+    output: Format,
+
+    /// Append every frame sent and received to this JSONL file, with byte
+    /// payloads decoded and secrets redacted
+    #[arg(long, global = true, value_name = "FILE")]
+    record: Option<PathBuf>,
+
     /// Enable verbose build logging
-    #[arg(long, default_value = "true")]
+    #[arg(long, global = true, default_value = "true")]
     verbose: bool,
 }
 
-async fn create_store(framed: &mut Framed<TcpStream, LengthDelimitedCodec>) -> Result<String> {
-    let store_command = ManagementCommand::NewStore {};
-    framed
-        .send(Bytes::from(serde_json::to_vec(&store_command)?))
-        .await?;
-
-    if let Some(response) = framed.next().await {
-        match response {
-            Ok(bytes) => {
-                let response: ManagementResponse = serde_json::from_slice(&bytes)?;
-                match response {
-                    ManagementResponse::StoreCreated { store_id } => {
-                        println!("Created new store with ID: {}", store_id);
-                        Ok(store_id)
-                    }
-                    _ => Err(anyhow::anyhow!("Unexpected response type")),
-                }
-            }
-            Err(e) => Err(e.into()),
-        }
-    } else {
-        Err(anyhow::anyhow!("No response received"))
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Open the REPL on an already running manager actor without bootstrapping
+    Attach {
+        /// ID of the running manager actor
+        #[arg(long)]
+        manager_id: String,
+    },
+    /// Build the child actor and wait for the build to finish
+    #[command(after_help = EXIT_CODES)]
+    Build(OneShot),
+    /// Request a change to the child actor and wait for it to be applied
+    #[command(after_help = EXIT_CODES)]
+    Change {
+        /// What to change, in plain words
+        description: String,
+        #[command(flatten)]
+        options: OneShot,
+    },
+    /// Print the manager's status
+    #[command(after_help = EXIT_CODES)]
+    Status(OneShot),
+    /// Start the child actor and wait for it to be running
+    #[command(after_help = EXIT_CODES)]
+    Start(OneShot),
+    /// Stop the child actor and wait for it to be stopped
+    #[command(after_help = EXIT_CODES)]
+    Stop(OneShot),
+    /// Show the frontend messages of a session recorded with --record
+    Replay {
+        /// Recording to replay
+        file: PathBuf,
+
+        /// Play back this many times faster than recorded
+        #[arg(long, default_value_t = 1.0, value_parser = parse_speed)]
+        speed: f64,
+
+        /// Show everything at once instead of at the recorded pace
+        #[arg(long, conflicts_with = "speed")]
+        instant: bool,
+
+        /// Only show messages about this operation
+        #[arg(long, value_name = "ID")]
+        operation: Option<String>,
+
+        /// Only show messages of this type, e.g. BuildEvent; may be repeated
+        #[arg(long = "type", value_name = "TYPE")]
+        types: Vec<String>,
+    },
 }
 
-async fn start_content_fs(
-    framed: &mut Framed<TcpStream, LengthDelimitedCodec>,
-    store_id: &str,
-) -> Result<String> {
-    let start_command = ManagementCommand::StartActor {
-        manifest: "/Users/colinrozzi/work/actors/runtime-content-fs/actor.toml".to_string(),
-        initial_state: Some(json!({ "store_id": store_id }).to_string().into_bytes()),
-    };
+const EXIT_CODES: &str = "Exit status: 0 on success, 1 if the operation failed, 2 if it could not be run or did not finish";
 
-    framed
-        .send(Bytes::from(serde_json::to_vec(&start_command)?))
-        .await?;
-
-    if let Some(response) = framed.next().await {
-        match response {
-            Ok(bytes) => {
-                let response: ManagementResponse = serde_json::from_slice(&bytes)?;
-                match response {
-                    ManagementResponse::ActorStarted { id } => {
-                        println!("Started runtime-content-fs with ID: {}", id);
-                        Ok(id)
-                    }
-                    _ => Err(anyhow::anyhow!("Unexpected response type")),
-                }
-            }
-            Err(e) => Err(e.into()),
+/// Options shared by the subcommands that send a single command.
+#[derive(clap::Args, Debug)]
+struct OneShot {
+    /// ID of the manager actor; defaults to the REPL actor of the session file
+    #[arg(long)]
+    manager_id: Option<String>,
+
+    /// Give up if the command has not finished after this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 600)]
+    timeout: u64,
+}
+
+impl Command {
+    /// The frontend command a one-shot subcommand sends, with its options.
+    fn one_shot(&self) -> Option<(FrontendCommand, &OneShot)> {
+        match self {
+            Command::Attach { .. } | Command::Replay { .. } => None,
+            Command::Build(options) => Some((FrontendCommand::BuildActor, options)),
+            Command::Change {
+                description,
+                options,
+            } => Some((
+                FrontendCommand::ChangeRequest {
+                    description: description.clone(),
+                },
+                options,
+            )),
+            Command::Status(options) => Some((FrontendCommand::GetStatus, options)),
+            Command::Start(options) => Some((FrontendCommand::StartActor, options)),
+            Command::Stop(options) => Some((FrontendCommand::StopActor, options)),
         }
-    } else {
-        Err(anyhow::anyhow!("No response received"))
     }
 }
 
-async fn start_actor_uploader(
-    framed: &mut Framed<TcpStream, LengthDelimitedCodec>,
-    runtime_content_fs_id: &str,
-) -> Result<String> {
-    let start_command = ManagementCommand::StartActor {
-        manifest: "/Users/colinrozzi/work/actors/actor-uploader/child-actor.toml".to_string(),
-        initial_state: Some(
-            json!({
-                "runtime_content_fs_address": runtime_content_fs_id
-            })
-            .to_string()
-            .into_bytes(),
-        ),
-    };
+fn parse_store_id(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(name, id)| (name.to_string(), id.to_string()))
+        .ok_or_else(|| format!("expected NAME=ID, got `{}`", arg))
+}
 
-    framed
-        .send(Bytes::from(serde_json::to_vec(&start_command)?))
-        .await?;
-
-    if let Some(response) = framed.next().await {
-        match response {
-            Ok(bytes) => {
-                let response: ManagementResponse = serde_json::from_slice(&bytes)?;
-                match response {
-                    ManagementResponse::ActorStarted { id } => {
-                        println!("Started actor-uploader with ID: {}", id);
-                        Ok(id)
-                    }
-                    _ => Err(anyhow::anyhow!("Unexpected response type")),
-                }
-            }
-            Err(e) => Err(e.into()),
-        }
-    } else {
-        Err(anyhow::anyhow!("No response received"))
+fn parse_speed(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!("expected a positive number, got `{}`", arg)),
     }
 }
 
-async fn start_manager_actor(
-    framed: &mut Framed<TcpStream, LengthDelimitedCodec>,
-    build_store_id: &str,
-    runtime_content_fs_id: &str,
-) -> Result<String> {
-    let api_key = env::var("ANTHROPIC_API_KEY")
-        .map_err(|_| anyhow::anyhow!("ANTHROPIC_API_KEY environment variable not set"))?;
-
-    let start_command = ManagementCommand::StartActor {
-        manifest: "/Users/colinrozzi/work/actors/manager/manifest.toml".to_string(),
-        initial_state: Some(
-            json!({
-                "build_store_id": build_store_id,
-                "runtime_content_fs_actor_id": runtime_content_fs_id,
-                "anthropic_api_key": api_key
-            })
-            .to_string()
-            .into_bytes(),
-        ),
-    };
+fn parse_secret(arg: &str) -> Result<(String, SecretSource), String> {
+    let (name, source) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=SOURCE, got `{}`", arg))?;
+    Ok((name.to_string(), source.parse()?))
+}
 
-    framed
-        .send(Bytes::from(serde_json::to_vec(&start_command)?))
-        .await?;
-
-    if let Some(response) = framed.next().await {
-        match response {
-            Ok(bytes) => {
-                let response: ManagementResponse = serde_json::from_slice(&bytes)?;
-                match response {
-                    ManagementResponse::ActorStarted { id } => {
-                        println!("Started manager actor with ID: {}", id);
-                        Ok(id)
-                    }
-                    _ => Err(anyhow::anyhow!("Unexpected response type {:#?}", response)),
-                }
-            }
-            Err(e) => Err(e.into()),
+/// The script the REPL runs instead of reading commands interactively:
+/// `--script`, where `-` stands for stdin, or stdin itself when it is not a
+/// terminal. Only called once the REPL is certain to run, since reading
+/// stdin blocks until it is closed.
+fn load_script(path: Option<&Path>, interactive: bool) -> Result<Option<Script>> {
+    Ok(match path {
+        Some(path) if path.as_os_str() == "-" => Some(Script::from_stdin()?),
+        Some(path) => Some(Script::load(path)?),
+        None if !interactive && !std::io::stdin().is_terminal() => Some(Script::from_stdin()?),
+        None => None,
+    })
+}
+
+/// Runs one command against the manager and returns whether it succeeded.
+async fn one_shot(
+    command: FrontendCommand,
+    options: &OneShot,
+    args: &Args,
+    transport: &Transport,
+) -> Result<bool> {
+    let session;
+    let manager_id = match &options.manager_id {
+        Some(id) => id.as_str(),
+        None => {
+            session = Session::load(&args.session_file)
+                .context("No --manager-id given and no session to take it from")?;
+            session.repl_actor_id()?
         }
-    } else {
-        Err(anyhow::anyhow!("No response received"))
-    }
+    };
+    oneshot::run(
+        manager_id,
+        &args.address,
+        transport,
+        command,
+        Duration::from_secs(options.timeout),
+        args.verbose,
+    )
+    .await
 }
 
-async fn check_actor_health(
-    framed: &mut Framed<TcpStream, LengthDelimitedCodec>,
-    actor_id: &str,
-) -> Result<()> {
-    let get_info_command = ManagementCommand::RequestActorMessage {
-        id: actor_id.to_string(),
-        data: json!({
-            "action": "get-info",
-            "params": []
-        })
-        .to_string()
-        .into_bytes(),
+/// Checks every actor recorded in a previous session, returning the names of
+/// the ones the server no longer runs or that fail their readiness probe.
+async fn dead_actors<'a>(
+    client: &mut ManagementClient,
+    profile: &Profile,
+    session: &'a Session,
+) -> Result<Vec<&'a str>> {
+    let running = if client.capabilities().supports("ListActors") {
+        Some(client.list_actors().await?)
+    } else {
+        None
     };
+    let mut dead = Vec::new();
+    for (name, id) in &session.actors {
+        say!("Checking {} ({}) from previous session...", name, id);
+        if running
+            .as_ref()
+            .is_some_and(|running| !running.contains(id))
+        {
+            say!("  {} is no longer running", name);
+            dead.push(name.as_str());
+            continue;
+        }
+        let probe = profile.readiness(name);
+        if !probe.enabled {
+            continue;
+        }
+        let timeout = Duration::from_secs(probe.deadline_secs);
+        if let Err(e) = probe.check(client, id, timeout).await {
+            say!("  {} is not responding: {}", name, e);
+            dead.push(name.as_str());
+        }
+    }
+    Ok(dead)
+}
 
-    framed
-        .send(Bytes::from(serde_json::to_vec(&get_info_command)?))
-        .await?;
-
-    if let Some(response) = framed.next().await {
-        match response {
-            Ok(bytes) => {
-                let response: ManagementResponse = serde_json::from_slice(&bytes)?;
-                match response {
-                    ManagementResponse::RequestedMessage { message, .. } => {
-                        let response_str = String::from_utf8(message)?;
-                        let response_json: serde_json::Value = serde_json::from_str(&response_str)?;
-
-                        if response_json.get("status") == Some(&"success".into()) {
-                            println!("Actor health check successful: {}", response_str);
-                            Ok(())
-                        } else {
-                            Err(anyhow::anyhow!(
-                                "Actor health check failed: {}",
-                                response_str
-                            ))
-                        }
-                    }
-                    _ => Err(anyhow::anyhow!("Unexpected response type")),
-                }
+/// Stops the actors of a previous session that are still running, so the
+/// fresh ones started in their place do not leave them orphaned.
+async fn stop_survivors(client: &mut ManagementClient, session: &Session, dead: &[&str]) {
+    if session
+        .actors
+        .keys()
+        .all(|name| dead.contains(&name.as_str()))
+    {
+        return;
+    }
+    say!("Stopping the rest of the previous session...");
+    for (name, id) in session.actors.iter().rev() {
+        if dead.contains(&name.as_str()) {
+            continue;
+        }
+        match client.stop_actor(id).await {
+            Ok(()) => {
+                say!("  Stopped {} ({})", name, id);
+                output::emit("actor_stopped", json!({ "name": name, "id": id }));
+            }
+            Err(e) => {
+                say!("  Could not stop {} ({}): {}", name, id, e);
+                output::emit(
+                    "actor_stop_failed",
+                    json!({ "name": name, "id": id, "error": e.to_string() }),
+                );
             }
-            Err(e) => Err(e.into()),
         }
-    } else {
-        Err(anyhow::anyhow!("No response received"))
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    output::set_format(args.output);
+    if let Some(path) = &args.record {
+        recording::start(path)?;
+    }
 
-    // Connect to the theater server
-    let socket = TcpStream::connect(&args.address).await?;
-
-    // Set up the framed connection
-    let mut codec = LengthDelimitedCodec::new();
-    codec.set_max_frame_length(32 * 1024 * 1024); // 32MB max frame size
-    let mut framed = Framed::new(socket, codec);
-
-    // Get store ID either from arg or by creating new store
-    let store_id = match (args.new_store, args.store_id) {
-        (true, _) => create_store(&mut framed).await?,
-        (false, Some(id)) => id,
-        (false, None) => {
-            return Err(anyhow::anyhow!(
-                "Please provide either --new-store flag or --store-id <ID>"
-            ))
+    if let Some(Command::Replay {
+        file,
+        speed,
+        instant,
+        operation,
+        types,
+    }) = &args.command
+    {
+        let (messages, skipped) = replay::messages(&replay::load(file)?)?;
+        if skipped > 0 {
+            say!(
+                "Skipping {} channel message(s) that are not frontend messages",
+                skipped
+            );
         }
-    };
+        let filter = Filter {
+            operation: operation.clone(),
+            types: types.clone(),
+        };
+        let pace = if *instant {
+            Pace::Instant
+        } else {
+            Pace::Recorded { speed: *speed }
+        };
+        let shown = replay::play(messages, &filter, pace, args.verbose).await;
+        say!("Replayed {} message(s) from {}", shown, file.display());
+        return Ok(());
+    }
 
-    // Get or create build store ID
-    let build_store_id = match args.build_store_id {
-        Some(id) => id,
-        None => {
-            println!("Creating new build store...");
-            create_store(&mut framed).await?
-        }
+    let transport = if args.tls {
+        Transport::tls(&TlsOptions {
+            ca: args.tls_ca.clone(),
+            cert: args.tls_cert.clone(),
+            key: args.tls_key.clone(),
+            server_name: args.tls_server_name.clone(),
+        })?
+    } else {
+        Transport::Tcp
     };
 
-    // Start the content-fs actor with the store id
-    let content_fs_id = start_content_fs(&mut framed, &store_id).await?;
+    if let Some((command, options)) = args.command.as_ref().and_then(Command::one_shot) {
+        let code = match one_shot(command, options, &args, &transport).await {
+            Ok(true) => 0,
+            Ok(false) => oneshot::EXIT_FAILED,
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                oneshot::EXIT_ERROR
+            }
+        };
+        std::process::exit(code);
+    }
 
-    // Check actor health
-    println!("Checking runtime-content-fs health...");
-    check_actor_health(&mut framed, &content_fs_id).await?;
+    if let Some(Command::Attach { manager_id }) = &args.command {
+        let script = load_script(args.script.as_deref(), args.interactive)?;
+        return repl::attach(manager_id, &args.address, &transport, args.verbose, script).await;
+    }
 
-    // If we created a new store, start the actor uploader
-    if args.new_store {
-        println!("Starting actor uploader to upload template child actor...");
-        let uploader_id = start_actor_uploader(&mut framed, &content_fs_id).await?;
-        println!("Successfully created store {} and started actors:\n  runtime-content-fs: {}\n  actor-uploader: {}", 
-            store_id, content_fs_id, uploader_id);
-    } else {
-        println!(
-            "Successfully started runtime-content-fs {} with existing store {}",
-            content_fs_id, store_id
+    let mut profile = Profile::load(&args.profile)?;
+    profile.secrets.extend(args.secrets);
+    recording::manifests_relative_to(&profile.dir);
+    // Manifests are opened by the server, so a remote one may well have
+    // files this machine does not
+    if let Err(e) = profile.check_manifests() {
+        let e = e.context(format!("Invalid profile {}", args.profile.display()));
+        if transport::is_local(&args.address) {
+            return Err(e);
+        }
+        say!(
+            "Warning: {:#}; assuming the server at {} has it",
+            e,
+            args.address
         );
     }
 
-    // Start the manager actor
-    println!("Starting manager actor...");
-    let manager_id = start_manager_actor(&mut framed, &build_store_id, &content_fs_id).await?;
+    // Store IDs supplied on the command line; the dedicated flags are
+    // shorthands for the stores in the default profile
+    let mut store_ids: HashMap<String, String> = args.stores.into_iter().collect();
+    if let Some(id) = args.store_id {
+        store_ids.insert("runtime".to_string(), id);
+    }
+    if let Some(id) = args.build_store_id {
+        store_ids.insert("build".to_string(), id);
+    }
+
+    // Walk the bootstrap against a client that prints instead of sending
+    if args.dry_run {
+        say!(
+            "Dry run: management commands that would be sent to {}\n",
+            args.address
+        );
+        let mut client = ManagementClient::dry_run();
+        let started = StartedActors::default();
+        bootstrap::run(&mut client, &profile, &store_ids, args.new_store, &started).await?;
+        say!("\nDry run complete, nothing was sent");
+        return Ok(());
+    }
 
-    println!("\nSystem is ready with:");
-    println!("  Runtime Store ID: {}", store_id);
-    println!("  Build Store ID: {}", build_store_id);
-    println!("  Runtime Content FS Actor ID: {}", content_fs_id);
-    println!("  Manager Actor ID: {}", manager_id);
+    // Read the script before connecting so a mistake in it is reported
+    // before anything is started
+    let script = load_script(args.script.as_deref(), args.interactive)?;
 
-    // Start the REPL connected to the manager actor
-    println!("\nStarting REPL session...");
-    println!(
+    let mut client = ManagementClient::connect(&args.address, &transport).await?;
+    say!("Connected to {} ({})", args.address, client.capabilities());
+    output::emit(
+        "connected",
+        json!({ "address": args.address, "capabilities": client.capabilities().to_string() }),
+    );
+
+    // Ctrl-C or SIGTERM winds down whatever is running, after which the
+    // actors started so far are stopped below as on a normal exit; the REPL
+    // handles Ctrl-C itself
+    tokio::spawn(async {
+        let signal = teardown::shutdown_signal().await;
+        say!("\n{}, shutting down...", signal);
+        teardown::begin_shutdown(signal);
+    });
+    let started = StartedActors::default();
+
+    // Reattach to the previous session if all of its actors are still alive,
+    // otherwise stop what is left of it and start fresh actors on top of its
+    // stores
+    let mut resumed = None;
+    if args.resume {
+        let previous = Session::load(&args.session_file)
+            .context("Nothing to resume; run without --resume to bootstrap a new session")?;
+        let dead = dead_actors(&mut client, &profile, &previous).await?;
+        // A session whose actors were stopped on exit keeps only its stores
+        if dead.is_empty() && previous.repl_actor_id().is_ok() {
+            say!(
+                "Reattaching to session from {}",
+                args.session_file.display()
+            );
+            resumed = Some(previous);
+        } else {
+            stop_survivors(&mut client, &previous, &dead).await;
+            say!("Starting new actors with the stores from the previous session...");
+            for (name, id) in previous.stores {
+                store_ids.entry(name).or_insert(id);
+            }
+        }
+    }
+
+    let mut session = match resumed {
+        Some(session) => session,
+        None => {
+            let system =
+                bootstrap::run(&mut client, &profile, &store_ids, args.new_store, &started)
+                    .await
+                    .map_err(exit_if_shutting_down)?;
+            let session = Session::new(&profile.repl, &system);
+            session.save(&args.session_file)?;
+            session
+        }
+    };
+
+    say!("\nSystem is ready with:");
+    for (name, id) in &session.stores {
+        say!("  Store {}: {}", name, id);
+    }
+    for (name, id) in &session.actors {
+        say!("  Actor {}: {}", name, id);
+    }
+    say!("  Session file: {}", args.session_file.display());
+    output::emit(
+        "ready",
+        json!({
+            "stores": session.stores,
+            "actors": session.actors,
+            "session_file": args.session_file,
+        }),
+    );
+
+    // Start the REPL connected to the profile's REPL actor
+    say!("\nStarting REPL session...");
+    say!(
         "Verbose build logging: {}",
         if args.verbose { "enabled" } else { "disabled" }
     );
-    repl::run_repl(&manager_id, &args.address, args.verbose).await?;
+    let result = repl::run_repl(
+        session.repl_actor_id()?,
+        &args.address,
+        &transport,
+        args.verbose,
+        script,
+    )
+    .await;
+
+    if args.keep_running {
+        say!("Leaving actors running (--keep-running)");
+    } else {
+        let report = started.stop_all(&mut client).await;
+        if !report.stopped.is_empty() {
+            session.forget_actors(&report.stopped);
+            session.save(&args.session_file)?;
+        }
+    }
 
+    result.map_err(exit_if_shutting_down)?;
+    if let Some(signal) = teardown::shutting_down() {
+        std::process::exit(signal.exit_code());
+    }
     Ok(())
 }
+
+/// Once shutdown has begun, reports `error` and exits with the status of
+/// the signal, which is what callers of the process need to see; otherwise
+/// hands `error` back.
+fn exit_if_shutting_down(error: anyhow::Error) -> anyhow::Error {
+    if let Some(signal) = teardown::shutting_down() {
+        eprintln!("Error: {:#}", error);
+        std::process::exit(signal.exit_code());
+    }
+    error
+}
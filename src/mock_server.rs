@@ -0,0 +1,564 @@
+use crate::protocol::*;
+use crate::transport::{Listener, Stream};
+use anyhow::Result;
+use bytes::Bytes;
+use futures::sink::SinkExt;
+use futures::stream::StreamExt;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+/// Behaviour knobs for the mock theater server.
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    /// Pause between the frontend messages of a simulated operation.
+    pub step_delay: Duration,
+    /// Make every simulated build fail.
+    pub fail_builds: bool,
+    /// Print every command received.
+    pub log: bool,
+    /// Reject the handshake like a server that predates it.
+    pub legacy: bool,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            step_delay: Duration::from_millis(200),
+            fail_builds: false,
+            log: false,
+            legacy: false,
+        }
+    }
+}
+
+/// In-memory stand-in for a theater server. Every management command is
+/// handled against in-memory stores, actors and channels, and any actor a
+/// channel is opened to behaves like a manager actor: frontend commands sent
+/// on the channel are answered with the status, operation and build event
+/// messages the real manager emits.
+pub async fn serve(listener: Listener, config: MockConfig) -> Result<()> {
+    let state = Arc::new(Mutex::new(State::default()));
+    let mut connections = 0u64;
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        connections += 1;
+        let connection = connections;
+        if config.log {
+            println!("[conn {}] connected from {}", connection, peer);
+        }
+
+        let state = state.clone();
+        let config = config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, connection, state, config.clone()).await {
+                if config.log {
+                    println!("[conn {}] error: {}", connection, e);
+                }
+            }
+        });
+    }
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    stores: Vec<String>,
+    actors: HashMap<String, MockActor>,
+    channels: HashMap<String, String>,
+    /// Subscription ID to the subscribed actor and the subscriber's connection.
+    subscriptions: HashMap<String, (String, Outgoing)>,
+}
+
+impl State {
+    fn id(&mut self, kind: &str) -> String {
+        self.next_id += 1;
+        format!("mock-{}-{}", kind, self.next_id)
+    }
+
+    /// Appends an event to the actor's chain and streams it to subscribers.
+    fn record(&mut self, actor_id: &str, event_type: &str, data: &[u8]) {
+        let Some(actor) = self.actors.get_mut(actor_id) else {
+            return;
+        };
+        let event = json!({
+            "event_type": event_type,
+            "sequence": actor.events.len(),
+            "data": data,
+        });
+        actor.events.push(event.clone());
+        for (subscribed, out) in self.subscriptions.values() {
+            if subscribed == actor_id {
+                let _ = out.send(ManagementResponse::ActorEvent {
+                    id: actor_id.to_string(),
+                    event: event.clone(),
+                });
+            }
+        }
+    }
+}
+
+struct MockActor {
+    manifest: String,
+    state: Option<Vec<u8>>,
+    events: Vec<serde_json::Value>,
+    child_running: bool,
+    active_operations: Vec<OperationSummary>,
+}
+
+type Outgoing = mpsc::UnboundedSender<ManagementResponse>;
+
+async fn handle_connection(
+    stream: Box<dyn Stream>,
+    connection: u64,
+    state: Arc<Mutex<State>>,
+    config: MockConfig,
+) -> Result<()> {
+    let mut codec = LengthDelimitedCodec::new();
+    codec.set_max_frame_length(32 * 1024 * 1024);
+    let (mut sink, mut frames) = Framed::new(stream, codec).split();
+
+    // Responses and simulated channel messages share one writer so frames
+    // are never interleaved
+    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<ManagementResponse>();
+    let writer = tokio::spawn(async move {
+        while let Some(response) = out_rx.recv().await {
+            let bytes = serde_json::to_vec(&response)?;
+            sink.send(Bytes::from(bytes)).await?;
+        }
+        Ok::<_, anyhow::Error>(())
+    });
+
+    while let Some(frame) = frames.next().await {
+        let frame = frame?;
+        match serde_json::from_slice::<ManagementCommand>(&frame) {
+            Ok(command) => {
+                // A StartActor arrives with its secrets filled in, so the log
+                // names commands without printing them
+                if config.log {
+                    println!("[conn {}] {}", connection, command.name());
+                }
+                handle_command(command, &state, &config, &out_tx);
+            }
+            Err(e) => {
+                let _ = out_tx.send(ManagementResponse::Error {
+                    message: format!("Invalid command: {}", e),
+                });
+            }
+        }
+    }
+
+    drop(out_tx);
+    writer.await?
+}
+
+fn handle_command(
+    command: ManagementCommand,
+    state: &Arc<Mutex<State>>,
+    config: &MockConfig,
+    out: &Outgoing,
+) {
+    let mut guard = state.lock().unwrap();
+    let s = &mut *guard;
+    let mut follow_up = None;
+
+    let response = match command {
+        ManagementCommand::Handshake { .. } if config.legacy => ManagementResponse::Error {
+            message: "Invalid command: unknown variant `Handshake`".to_string(),
+        },
+        ManagementCommand::Handshake { .. } => ManagementResponse::ServerInfo {
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: PROTOCOL_VERSION,
+            commands: ManagementCommand::NAMES
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+        },
+        ManagementCommand::NewStore {} => {
+            let store_id = s.id("store");
+            s.stores.push(store_id.clone());
+            ManagementResponse::StoreCreated { store_id }
+        }
+        ManagementCommand::StartActor {
+            manifest,
+            initial_state,
+        } => {
+            let id = s.id("actor");
+            s.actors.insert(
+                id.clone(),
+                MockActor {
+                    manifest,
+                    state: initial_state.clone(),
+                    events: Vec::new(),
+                    child_running: false,
+                    active_operations: Vec::new(),
+                },
+            );
+            s.record(&id, "actor-started", &initial_state.unwrap_or_default());
+            ManagementResponse::ActorStarted { id }
+        }
+        ManagementCommand::StopActor { id } => match s.actors.remove(&id) {
+            Some(_) => {
+                s.channels.retain(|_, actor| *actor != id);
+                s.subscriptions.retain(|_, (actor, _)| *actor != id);
+                ManagementResponse::ActorStopped { id }
+            }
+            None => not_found(&id),
+        },
+        ManagementCommand::RestartActor { id } => match s.actors.get_mut(&id) {
+            Some(actor) => {
+                actor.child_running = false;
+                actor.active_operations.clear();
+                s.record(&id, "actor-restarted", &[]);
+                ManagementResponse::ActorRestarted { id }
+            }
+            None => not_found(&id),
+        },
+        ManagementCommand::ListActors {} => {
+            let mut actors: Vec<String> = s.actors.keys().cloned().collect();
+            actors.sort();
+            ManagementResponse::ActorList { actors }
+        }
+        ManagementCommand::GetActorState { id } => match s.actors.get(&id) {
+            Some(actor) => ManagementResponse::ActorState {
+                state: actor.state.clone(),
+                id,
+            },
+            None => not_found(&id),
+        },
+        ManagementCommand::GetActorEvents { id } => match s.actors.get(&id) {
+            Some(actor) => ManagementResponse::ActorEvents {
+                events: actor.events.clone(),
+                id,
+            },
+            None => not_found(&id),
+        },
+        ManagementCommand::SendActorMessage { id, data } => {
+            if s.actors.contains_key(&id) {
+                s.record(&id, "message-received", &data);
+                ManagementResponse::SentMessage { id }
+            } else {
+                not_found(&id)
+            }
+        }
+        ManagementCommand::SubscribeToActor { id } => {
+            if s.actors.contains_key(&id) {
+                let subscription_id = s.id("subscription");
+                s.subscriptions
+                    .insert(subscription_id.clone(), (id.clone(), out.clone()));
+                ManagementResponse::Subscribed {
+                    id,
+                    subscription_id,
+                }
+            } else {
+                not_found(&id)
+            }
+        }
+        ManagementCommand::UnsubscribeFromActor {
+            id,
+            subscription_id,
+        } => match s.subscriptions.remove(&subscription_id) {
+            Some(_) => ManagementResponse::Unsubscribed { id },
+            None => ManagementResponse::Error {
+                message: format!("Subscription not found: {}", subscription_id),
+            },
+        },
+        ManagementCommand::RequestActorMessage { id, data } => match s.actors.get(&id) {
+            Some(actor) => {
+                let request: serde_json::Value = serde_json::from_slice(&data).unwrap_or_default();
+                let reply = match request.get("action").and_then(|a| a.as_str()) {
+                    Some("get-info") => json!({
+                        "status": "success",
+                        "actor_id": id,
+                        "manifest": actor.manifest,
+                    }),
+                    _ => json!({
+                        "status": "error",
+                        "message": "Unknown action",
+                    }),
+                };
+                ManagementResponse::RequestedMessage {
+                    id,
+                    message: reply.to_string().into_bytes(),
+                }
+            }
+            None => not_found(&id),
+        },
+        ManagementCommand::OpenChannel { actor_id, .. } => match &actor_id {
+            ChannelParticipant::Actor(id) if s.actors.contains_key(id) => {
+                let channel_id = s.id("channel");
+                s.channels.insert(channel_id.clone(), id.clone());
+                ManagementResponse::ChannelOpened {
+                    channel_id,
+                    actor_id,
+                }
+            }
+            ChannelParticipant::Actor(id) => not_found(id),
+            ChannelParticipant::External => ManagementResponse::Error {
+                message: "Channels can only be opened to actors".to_string(),
+            },
+        },
+        ManagementCommand::SendOnChannel {
+            channel_id,
+            message,
+        } => match s.channels.get(&channel_id) {
+            Some(actor_id) => {
+                if let Ok(command) = serde_json::from_slice::<FrontendCommand>(&message) {
+                    let manager = Manager {
+                        state: state.clone(),
+                        out: out.clone(),
+                        channel_id: channel_id.clone(),
+                        actor_id: actor_id.clone(),
+                        config: config.clone(),
+                    };
+                    follow_up = Some((manager, command));
+                }
+                ManagementResponse::MessageSent { channel_id }
+            }
+            None => ManagementResponse::Error {
+                message: format!("Channel not found: {}", channel_id),
+            },
+        },
+        ManagementCommand::CloseChannel { channel_id } => match s.channels.remove(&channel_id) {
+            Some(_) => ManagementResponse::ChannelClosed { channel_id },
+            None => ManagementResponse::Error {
+                message: format!("Channel not found: {}", channel_id),
+            },
+        },
+    };
+    drop(guard);
+
+    let _ = out.send(response);
+
+    // The simulated manager answers only after MessageSent has been queued
+    if let Some((manager, command)) = follow_up {
+        tokio::spawn(manager.handle(command));
+    }
+}
+
+fn not_found(id: &str) -> ManagementResponse {
+    ManagementResponse::Error {
+        message: format!("Actor not found: {}", id),
+    }
+}
+
+/// Simulated manager actor behind one channel.
+struct Manager {
+    state: Arc<Mutex<State>>,
+    out: Outgoing,
+    channel_id: String,
+    actor_id: String,
+    config: MockConfig,
+}
+
+impl Manager {
+    async fn handle(self, command: FrontendCommand) {
+        match command {
+            FrontendCommand::GetStatus => {
+                let status = self.with_actor(|actor| FrontendMessage::Status {
+                    child_running: actor.child_running,
+                    active_operations: actor.active_operations.clone(),
+                });
+                if let Some(status) = status {
+                    self.emit(status);
+                }
+            }
+            FrontendCommand::StartActor => self.start_child().await,
+            FrontendCommand::StopActor => self.stop_child().await,
+            FrontendCommand::BuildActor => self.build().await,
+            FrontendCommand::ChangeRequest { description } => self.change(description).await,
+            FrontendCommand::Disconnect => {}
+        }
+    }
+
+    async fn start_child(&self) {
+        let op = self
+            .begin(OperationType::Start, "Starting child actor")
+            .await;
+        let child_id = format!("{}-child", self.actor_id);
+        self.with_actor(|actor| actor.child_running = true);
+        self.step(FrontendMessage::ChildStarted {
+            child_id: child_id.clone(),
+        })
+        .await;
+        self.complete(&op, true, &format!("Child actor {} started", child_id))
+            .await;
+    }
+
+    async fn stop_child(&self) {
+        let op = self
+            .begin(OperationType::Stop, "Stopping child actor")
+            .await;
+        let was_running = self
+            .with_actor(|actor| std::mem::replace(&mut actor.child_running, false))
+            .unwrap_or(false);
+        if was_running {
+            self.step(FrontendMessage::ChildStopped {
+                child_id: format!("{}-child", self.actor_id),
+            })
+            .await;
+            self.complete(&op, true, "Child actor stopped").await;
+        } else {
+            self.complete(&op, false, "Child actor is not running")
+                .await;
+        }
+    }
+
+    async fn build(&self) {
+        let op = self
+            .begin(OperationType::Build, "Building child actor")
+            .await;
+        let success = !self.config.fail_builds;
+
+        self.build_event(&op, BuildEventType::Log, "Preparing build workspace", |d| {
+            d.level = Some("info".to_string())
+        })
+        .await;
+        self.build_event(&op, BuildEventType::FileExtracted, "src/lib.rs", |_| {})
+            .await;
+        self.build_event(&op, BuildEventType::CommandStarted, "cargo", |d| {
+            d.command = Some("cargo".to_string());
+            d.args = Some(vec![
+                "component".to_string(),
+                "build".to_string(),
+                "--release".to_string(),
+            ]);
+        })
+        .await;
+        for percent in [25.0, 50.0, 75.0] {
+            self.build_event(&op, BuildEventType::Progress, "Compiling", |d| {
+                d.percent_complete = Some(percent);
+                d.status = Some("Compiling".to_string());
+            })
+            .await;
+        }
+        self.build_event(&op, BuildEventType::CommandOutput, "cargo", |d| {
+            if success {
+                d.stdout = Some("   Compiling child v0.1.0\n    Finished release".to_string());
+            } else {
+                d.stderr = Some("error[E0425]: cannot find value `x` in this scope".to_string());
+            }
+        })
+        .await;
+
+        let message = if success {
+            "Build succeeded"
+        } else {
+            "Build failed"
+        };
+        self.build_event(&op, BuildEventType::BuildComplete, message, |d| {
+            d.success = Some(success);
+            if success {
+                d.wasm_path = Some("target/wasm32-wasi/release/child.wasm".to_string());
+                d.wasm_hash = Some("3f2a9c0d".to_string());
+            } else {
+                d.error = Some("cargo exited with status 101".to_string());
+            }
+        })
+        .await;
+        self.complete(&op, success, message).await;
+    }
+
+    async fn change(&self, description: String) {
+        let op = self
+            .begin(OperationType::Change, &format!("Change: {}", description))
+            .await;
+        self.step(FrontendMessage::Log {
+            level: "info".to_string(),
+            message: format!("Applying change request: {}", description),
+        })
+        .await;
+        self.step(FrontendMessage::OperationProgress {
+            operation_id: op.clone(),
+            description: "Editing source".to_string(),
+            percent_complete: 50.0,
+        })
+        .await;
+        self.complete(&op, true, "Change applied").await;
+    }
+
+    /// Registers an active operation and announces it.
+    async fn begin(&self, operation_type: OperationType, description: &str) -> String {
+        let operation_id = {
+            let mut state = self.state.lock().unwrap();
+            let operation_id = state.id("op");
+            if let Some(actor) = state.actors.get_mut(&self.actor_id) {
+                actor.active_operations.push(OperationSummary {
+                    operation_id: operation_id.clone(),
+                    operation_type: operation_type.clone(),
+                    description: description.to_string(),
+                });
+            }
+            operation_id
+        };
+        self.step(FrontendMessage::OperationStarted {
+            operation_id: operation_id.clone(),
+            operation_type,
+            description: description.to_string(),
+        })
+        .await;
+        operation_id
+    }
+
+    async fn complete(&self, operation_id: &str, success: bool, message: &str) {
+        self.with_actor(|actor| {
+            actor
+                .active_operations
+                .retain(|op| op.operation_id != operation_id)
+        });
+        self.step(FrontendMessage::OperationCompleted {
+            operation_id: operation_id.to_string(),
+            success,
+            message: message.to_string(),
+        })
+        .await;
+    }
+
+    async fn build_event(
+        &self,
+        operation_id: &str,
+        event_type: BuildEventType,
+        message: &str,
+        details: impl FnOnce(&mut BuildEventDetails),
+    ) {
+        let mut build_details = BuildEventDetails::default();
+        details(&mut build_details);
+        self.step(FrontendMessage::BuildEvent {
+            operation_id: operation_id.to_string(),
+            event_type,
+            message: message.to_string(),
+            details: build_details,
+        })
+        .await;
+    }
+
+    async fn step(&self, message: FrontendMessage) {
+        tokio::time::sleep(self.config.step_delay).await;
+        self.emit(message);
+    }
+
+    fn emit(&self, message: FrontendMessage) {
+        let message = match serde_json::to_vec(&message) {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+        let _ = self.out.send(ManagementResponse::ChannelMessage {
+            channel_id: self.channel_id.clone(),
+            sender_id: ChannelParticipant::Actor(self.actor_id.clone()),
+            message,
+        });
+    }
+
+    fn with_actor<T>(&self, f: impl FnOnce(&mut MockActor) -> T) -> Option<T> {
+        self.state
+            .lock()
+            .unwrap()
+            .actors
+            .get_mut(&self.actor_id)
+            .map(f)
+    }
+}
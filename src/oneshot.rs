@@ -0,0 +1,148 @@
+use crate::protocol::*;
+use crate::repl::{display_event, display_message, ChannelRepl, ReplEvent};
+use crate::transport::Transport;
+use anyhow::Result;
+use std::time::Duration;
+
+/// Exit code for a command the actor ran but reported as failed.
+pub const EXIT_FAILED: i32 = 1;
+/// Exit code for a command that could not be run or did not finish.
+pub const EXIT_ERROR: i32 = 2;
+
+/// How long the actor has to answer a request for its status.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Opens a channel to `actor_id`, runs one command to completion and
+/// returns whether it succeeded.
+pub async fn run(
+    actor_id: &str,
+    address: &str,
+    transport: &Transport,
+    command: FrontendCommand,
+    timeout: Duration,
+    verbose: bool,
+) -> Result<bool> {
+    let mut repl = ChannelRepl::new(address, transport, actor_id).await?;
+    let outcome = tokio::time::timeout(timeout, execute(&mut repl, command, verbose)).await;
+    // The caller exits right after, so the channel must be closed by now
+    repl.close().await;
+    let outcome =
+        outcome.map_err(|_| anyhow::anyhow!("Command did not finish within {:?}", timeout))??;
+    Ok(succeeded(&outcome))
+}
+
+/// Sends `command` and waits for the message that settles it: the `Status`
+/// for `GetStatus`, otherwise the `OperationCompleted` of the operation the
+/// command started. Everything received on the way is displayed.
+///
+/// The actor's messages carry no reference to the command that caused them,
+/// so the status is asked for first: operations running by then, and errors
+/// sent before it, belong to someone else.
+pub async fn execute(
+    repl: &mut ChannelRepl,
+    command: FrontendCommand,
+    verbose: bool,
+) -> Result<FrontendMessage> {
+    let expected = match &command {
+        FrontendCommand::StartActor => Some(OperationType::Start),
+        FrontendCommand::StopActor => Some(OperationType::Stop),
+        FrontendCommand::BuildActor => Some(OperationType::Build),
+        FrontendCommand::ChangeRequest { .. } => Some(OperationType::Change),
+        FrontendCommand::GetStatus => None,
+        FrontendCommand::Disconnect => anyhow::bail!("Disconnect does not start anything"),
+    };
+    let earlier = match expected {
+        Some(_) => active_operations(repl, verbose).await?,
+        None => Vec::new(),
+    };
+    repl.send(command).await?;
+
+    let mut operation = None;
+    loop {
+        let msg = next_message(repl, verbose).await?;
+        match (&msg, &expected, &operation) {
+            (FrontendMessage::Status { .. }, None, _) => {
+                display_message(&msg);
+                return Ok(msg);
+            }
+            // Once our operation runs, its failures arrive as its completion
+            (FrontendMessage::Error { code, message }, _, None) => {
+                anyhow::bail!("The actor reported error {}: {}", code, message)
+            }
+            (
+                FrontendMessage::OperationStarted {
+                    operation_id,
+                    operation_type,
+                    ..
+                },
+                Some(expected),
+                None,
+            ) if operation_type == expected && !earlier.contains(operation_id) => {
+                operation = Some(operation_id.clone());
+            }
+            (FrontendMessage::OperationCompleted { operation_id, .. }, _, Some(ours))
+                if operation_id == ours =>
+            {
+                display_message(&msg);
+                return Ok(msg);
+            }
+            _ => {}
+        }
+        display_event(&ReplEvent::Message(msg), verbose);
+    }
+}
+
+/// Asks the actor for its status and returns the IDs of the operations
+/// already running, displaying whatever arrives before the status.
+pub(crate) async fn active_operations(
+    repl: &mut ChannelRepl,
+    verbose: bool,
+) -> Result<Vec<String>> {
+    repl.send(FrontendCommand::GetStatus).await?;
+    let status = async {
+        loop {
+            let msg = next_message(repl, verbose).await?;
+            if let FrontendMessage::Status {
+                active_operations, ..
+            } = &msg
+            {
+                return Ok(active_operations
+                    .iter()
+                    .map(|op| op.operation_id.clone())
+                    .collect());
+            }
+            display_event(&ReplEvent::Message(msg), verbose);
+        }
+    };
+    tokio::time::timeout(STATUS_TIMEOUT, status)
+        .await
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "The actor did not report its status within {:?}",
+                STATUS_TIMEOUT
+            )
+        })?
+}
+
+/// Waits for the next message from the actor, displaying any other event
+/// on the way.
+pub async fn next_message(repl: &mut ChannelRepl, verbose: bool) -> Result<FrontendMessage> {
+    loop {
+        match repl.recv().await {
+            Some(ReplEvent::Message(msg)) => return Ok(msg),
+            Some(ReplEvent::GaveUp { attempts, error }) => {
+                anyhow::bail!("Connection lost after {} attempt(s): {}", attempts, error)
+            }
+            Some(event) => display_event(&event, verbose),
+            None => anyhow::bail!("Channel closed before the command finished"),
+        }
+    }
+}
+
+/// Whether the message that settled a command reports success.
+pub fn succeeded(outcome: &FrontendMessage) -> bool {
+    !matches!(
+        outcome,
+        FrontendMessage::OperationCompleted { success: false, .. }
+    )
+}
@@ -0,0 +1,313 @@
+use crate::protocol::{BuildEventType, FrontendMessage, OperationType};
+use crate::repl::render_message;
+use chrono::{DateTime, Local, Utc};
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// What the client has seen of one operation on the actor.
+#[derive(Debug)]
+pub struct Operation {
+    pub id: String,
+    /// Unknown when the operation was first seen after it started, through
+    /// its progress or build events.
+    pub operation_type: Option<OperationType>,
+    pub description: String,
+    pub started: DateTime<Utc>,
+    pub ended: Option<DateTime<Utc>>,
+    /// The last progress reported, as percent complete and description.
+    pub progress: Option<(f32, String)>,
+    /// Whether it succeeded and the completion message, once complete.
+    pub outcome: Option<(bool, String)>,
+    /// Every `BuildEvent` message about the operation, in order.
+    pub build_events: Vec<FrontendMessage>,
+}
+
+impl Operation {
+    fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            operation_type: None,
+            description: String::new(),
+            started: Utc::now(),
+            ended: None,
+            progress: None,
+            outcome: None,
+            build_events: Vec::new(),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    fn state(&self) -> &'static str {
+        match &self.outcome {
+            None => "running",
+            Some((true, _)) => "succeeded",
+            Some((false, _)) => "failed",
+        }
+    }
+
+    fn kind(&self) -> String {
+        match &self.operation_type {
+            Some(operation_type) => operation_type.to_string(),
+            None => "?".to_string(),
+        }
+    }
+
+    /// How long it ran, or has been running.
+    fn elapsed(&self) -> chrono::Duration {
+        self.ended.unwrap_or_else(Utc::now) - self.started
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "operation_id": self.id,
+            "operation_type": self.operation_type,
+            "description": self.description,
+            "started": self.started.to_rfc3339(),
+            "ended": self.ended.map(|ended| ended.to_rfc3339()),
+            "progress": self.progress.as_ref().map(|(percent, description)| {
+                json!({ "percent_complete": percent, "description": description })
+            }),
+            "success": self.outcome.as_ref().map(|(success, _)| success),
+            "message": self.outcome.as_ref().map(|(_, message)| message),
+            "build_events": self.build_events,
+        })
+    }
+}
+
+/// Every operation seen on a channel, oldest first.
+#[derive(Debug, Default)]
+pub struct Operations {
+    operations: Vec<Operation>,
+    /// Set once the channel is gone for good and nothing more will complete.
+    closed: bool,
+}
+
+impl Operations {
+    /// Updates the table with a message from the actor. Messages that are
+    /// not about an operation are ignored.
+    pub fn record(&mut self, msg: &FrontendMessage) {
+        match msg {
+            FrontendMessage::OperationStarted {
+                operation_id,
+                operation_type,
+                description,
+            } => {
+                let operation = self.entry(operation_id);
+                operation.operation_type = Some(operation_type.clone());
+                operation.description = description.clone();
+            }
+            FrontendMessage::OperationProgress {
+                operation_id,
+                description,
+                percent_complete,
+            } => {
+                self.entry(operation_id).progress = Some((*percent_complete, description.clone()));
+            }
+            FrontendMessage::OperationCompleted {
+                operation_id,
+                success,
+                message,
+            } => {
+                let operation = self.entry(operation_id);
+                operation.outcome = Some((*success, message.clone()));
+                operation.ended = Some(Utc::now());
+            }
+            FrontendMessage::BuildEvent {
+                operation_id,
+                event_type,
+                message,
+                details,
+            } => {
+                let operation = self.entry(operation_id);
+                if let (BuildEventType::Progress, Some(percent)) =
+                    (event_type, details.percent_complete)
+                {
+                    operation.progress = Some((percent, message.clone()));
+                }
+                operation.build_events.push(msg.clone());
+            }
+            // Operations that were running before we connected
+            FrontendMessage::Status {
+                active_operations, ..
+            } => {
+                for summary in active_operations {
+                    let operation = self.entry(&summary.operation_id);
+                    if operation.operation_type.is_none() {
+                        operation.operation_type = Some(summary.operation_type.clone());
+                        operation.description = summary.description.clone();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn entry(&mut self, id: &str) -> &mut Operation {
+        let index = match self.operations.iter().position(|op| op.id == id) {
+            Some(index) => index,
+            None => {
+                self.operations.push(Operation::new(id));
+                self.operations.len() - 1
+            }
+        };
+        &mut self.operations[index]
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Operation> {
+        self.operations.iter().find(|op| op.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Operation> {
+        self.operations.iter()
+    }
+
+    /// The IDs that start with `prefix`, for completion.
+    pub fn ids_starting_with<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> {
+        self.operations
+            .iter()
+            .map(|op| op.id.as_str())
+            .filter(move |id| id.starts_with(prefix))
+    }
+}
+
+/// An operation table shared between the task that receives messages and
+/// whoever asks about the operations.
+#[derive(Debug, Clone)]
+pub struct Tracker {
+    operations: Arc<watch::Sender<Operations>>,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Self {
+            operations: Arc::new(watch::Sender::new(Operations::default())),
+        }
+    }
+}
+
+impl Tracker {
+    pub fn record(&self, msg: &FrontendMessage) {
+        self.operations
+            .send_modify(|operations| operations.record(msg));
+    }
+
+    /// Calls `f` with the table as it is now.
+    pub fn with<R>(&self, f: impl FnOnce(&Operations) -> R) -> R {
+        f(&self.operations.borrow())
+    }
+
+    /// Records that no more messages will arrive, ending every wait.
+    pub fn close(&self) {
+        self.operations
+            .send_modify(|operations| operations.closed = true);
+    }
+
+    /// Waits until the operation `id` is complete, returning `false` if the
+    /// tracker was closed before it completed.
+    pub async fn wait(&self, id: &str) -> bool {
+        let complete =
+            |operations: &Operations| operations.get(id).is_some_and(Operation::is_complete);
+        let mut operations = self.operations.subscribe();
+        // The sender lives as long as `self`, so this cannot fail
+        let completed = match operations
+            .wait_for(|operations| operations.closed || complete(operations))
+            .await
+        {
+            Ok(operations) => complete(&operations),
+            Err(_) => false,
+        };
+        completed
+    }
+}
+
+/// Renders the table the way the `ops` command shows it.
+pub fn render_operations(operations: &Operations) -> String {
+    let mut out = String::new();
+    write_operations(&mut out, operations).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_operations(out: &mut impl fmt::Write, operations: &Operations) -> fmt::Result {
+    if operations.operations.is_empty() {
+        return writeln!(out, "No operations yet");
+    }
+    writeln!(out, "Operations:")?;
+    for op in operations.iter() {
+        write!(out, "  ")?;
+        write_summary(out, op)?;
+    }
+    Ok(())
+}
+
+/// Renders an operation as one line of the `ops` table.
+pub fn render_summary(operation: &Operation) -> String {
+    let mut out = String::new();
+    write_summary(&mut out, operation).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_summary(out: &mut impl fmt::Write, op: &Operation) -> fmt::Result {
+    let progress = match (&op.outcome, &op.progress) {
+        (None, Some((percent, _))) => format!(" {:.1}%", percent),
+        _ => String::new(),
+    };
+    writeln!(
+        out,
+        "{}  {:<6}  {}{}  {}s  {}",
+        op.id,
+        op.kind(),
+        op.state(),
+        progress,
+        op.elapsed().num_seconds(),
+        op.description
+    )
+}
+
+/// Renders one operation in full, with its build log, the way the `show`
+/// command shows it.
+pub fn render_operation(operation: &Operation) -> String {
+    let mut out = String::new();
+    write_operation(&mut out, operation).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_operation(out: &mut impl fmt::Write, op: &Operation) -> fmt::Result {
+    writeln!(out, "Operation {}:", op.id)?;
+    writeln!(out, "  Type: {}", op.kind())?;
+    if !op.description.is_empty() {
+        writeln!(out, "  Description: {}", op.description)?;
+    }
+    writeln!(
+        out,
+        "  Started: {}",
+        op.started.with_timezone(&Local).format("%H:%M:%S")
+    )?;
+    match op.ended {
+        Some(ended) => writeln!(
+            out,
+            "  Ended: {} ({}s)",
+            ended.with_timezone(&Local).format("%H:%M:%S"),
+            op.elapsed().num_seconds()
+        )?,
+        None => writeln!(out, "  Running for {}s", op.elapsed().num_seconds())?,
+    }
+    if let Some((percent, description)) = &op.progress {
+        writeln!(out, "  Progress: {:.1}% - {}", percent, description)?;
+    }
+    if let Some((success, message)) = &op.outcome {
+        let status = if *success { "✓" } else { "✗" };
+        writeln!(out, "  Result: {} {}", status, message)?;
+    }
+    if !op.build_events.is_empty() {
+        writeln!(out, "  Build log:")?;
+        for event in &op.build_events {
+            write!(out, "{}", render_message(event))?;
+        }
+    }
+    Ok(())
+}
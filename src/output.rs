@@ -0,0 +1,83 @@
+use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// How results are written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Human-readable text.
+    #[default]
+    Text,
+    /// One JSON object per line; progress text goes to stderr instead.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" | "ndjson" => Ok(Format::Json),
+            other => Err(format!(
+                "unknown output format `{}`; use text, json or ndjson",
+                other
+            )),
+        }
+    }
+}
+
+static FORMAT: OnceLock<Format> = OnceLock::new();
+
+/// Selects the output format for the rest of the process.
+pub fn set_format(format: Format) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn is_json() -> bool {
+    FORMAT.get() == Some(&Format::Json)
+}
+
+/// Writes a JSON record of the given kind to stdout. Does nothing unless the
+/// output format is JSON.
+pub fn emit(kind: &str, fields: Value) {
+    if is_json() {
+        println!("{}", record(kind, fields));
+    }
+}
+
+/// A record of the given kind with a timestamp and the fields of `fields`.
+pub fn record(kind: &str, fields: Value) -> Value {
+    let mut record = json!({ "timestamp": timestamp(), "kind": kind });
+    if let (Value::Object(record), Value::Object(fields)) = (&mut record, fields) {
+        record.extend(fields);
+    }
+    record
+}
+
+/// The current time in RFC 3339 with milliseconds, in UTC.
+pub fn timestamp() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+#[doc(hidden)]
+pub fn write_text(args: fmt::Arguments) {
+    if is_json() {
+        eprintln!("{}", args);
+    } else {
+        println!("{}", args);
+    }
+}
+
+/// Prints human-readable progress like `println!`, on stderr when stdout
+/// carries JSON records.
+#[macro_export]
+macro_rules! say {
+    () => {
+        $crate::output::write_text(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::write_text(format_args!($($arg)*))
+    };
+}
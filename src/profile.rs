@@ -0,0 +1,390 @@
+use crate::readiness::{ReadinessOverride, ReadinessProbe};
+use crate::secrets::SecretSource;
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Bootstrap profile: the stores to create or reuse and the graph of actors to
+/// start, in dependency order, before the REPL attaches to `repl`.
+///
+/// Initial state templates may reference `${stores.<name>}`,
+/// `${actors.<name>.id}` (which implies a dependency on that actor),
+/// `${secrets.<name>}` for values from a [`SecretSource`] and `${env.<VAR>}`
+/// for environment variables. Only secrets are redacted from output, so
+/// credentials belong under `[secrets]`, not in `${env.<VAR>}`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Actor whose channel the REPL opens once bootstrap completes.
+    #[serde(default = "default_repl_actor")]
+    pub repl: String,
+    /// Readiness probe settings every actor starts from.
+    #[serde(default)]
+    pub readiness: ReadinessProbe,
+    #[serde(default)]
+    pub secrets: IndexMap<String, SecretSource>,
+    #[serde(default)]
+    pub stores: IndexMap<String, StoreSpec>,
+    pub actors: IndexMap<String, ActorSpec>,
+    /// Directory of the profile file, which relative manifests are anchored
+    /// to.
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StoreSpec {
+    #[serde(default)]
+    pub create: StoreCreation,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StoreCreation {
+    /// Create the store unless an ID was supplied on the command line.
+    #[default]
+    IfMissing,
+    /// Only create the store when `--new-store` is passed; otherwise an ID
+    /// must be supplied.
+    OnNewStore,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActorSpec {
+    pub manifest: PathBuf,
+    #[serde(default)]
+    pub initial_state: Option<Value>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Only start this actor when the named store was created by this run.
+    /// Actors that depend on it are left out along with it.
+    #[serde(default)]
+    pub when_created: Option<String>,
+    /// Fields replacing those of the profile's `[readiness]` for this actor.
+    #[serde(default)]
+    pub readiness: ReadinessOverride,
+}
+
+fn default_repl_actor() -> String {
+    "manager".to_string()
+}
+
+impl Profile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| {
+            format!(
+                "Could not read bootstrap profile {}; pass --profile <FILE> or set MANAGER_INTERFACE_PROFILE (see manager-interface.example.toml)",
+                path.display()
+            )
+        })?;
+
+        let mut profile: Profile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid JSON profile {}", path.display()))?,
+            _ => toml::from_str(&contents)
+                .with_context(|| format!("Invalid TOML profile {}", path.display()))?,
+        };
+
+        // Manifests are resolved by the theater server, so relative paths are
+        // anchored to the profile's directory and sent as absolute paths.
+        let canonical = path
+            .canonicalize()
+            .with_context(|| format!("Could not resolve profile path {}", path.display()))?;
+        profile.dir = canonical.parent().unwrap_or(Path::new("/")).to_path_buf();
+        for spec in profile.actors.values_mut() {
+            if spec.manifest.is_relative() {
+                spec.manifest = profile.dir.join(&spec.manifest);
+            }
+        }
+
+        profile
+            .validate()
+            .with_context(|| format!("Invalid profile {}", path.display()))?;
+
+        Ok(profile)
+    }
+
+    /// Fails if any actor's manifest is not a file on this machine. Only
+    /// meaningful when the server shares this machine's filesystem.
+    pub fn check_manifests(&self) -> Result<()> {
+        for (name, spec) in &self.actors {
+            if !spec.manifest.is_file() {
+                anyhow::bail!(
+                    "Manifest for actor `{}` not found: {}",
+                    name,
+                    spec.manifest.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !self.actors.contains_key(&self.repl) {
+            anyhow::bail!("REPL actor `{}` is not declared under [actors]", self.repl);
+        }
+
+        for (name, spec) in &self.actors {
+            spec.references()
+                .and_then(|references| references.iter().try_for_each(|r| check_reference(r)))
+                .with_context(|| format!("Invalid initial_state for actor `{}`", name))?;
+            for dependency in self.dependencies(name)? {
+                if !self.actors.contains_key(&dependency) {
+                    anyhow::bail!("Actor `{}` depends on unknown actor `{}`", name, dependency);
+                }
+            }
+            if let Some(store) = &spec.when_created {
+                if !self.stores.contains_key(store) {
+                    anyhow::bail!(
+                        "Actor `{}` is conditional on unknown store `{}`",
+                        name,
+                        store
+                    );
+                }
+            }
+            for reference in spec.references()? {
+                if let Some(store) = reference.strip_prefix("stores.") {
+                    if !self.stores.contains_key(store) {
+                        anyhow::bail!("Actor `{}` references unknown store `{}`", name, store);
+                    }
+                }
+            }
+        }
+
+        self.start_order().map(|_| ())
+    }
+
+    /// The profile's readiness probe with the actor's own fields applied.
+    pub fn readiness(&self, actor: &str) -> ReadinessProbe {
+        match self.actors.get(actor) {
+            Some(spec) => self.readiness.overridden(&spec.readiness),
+            None => self.readiness.clone(),
+        }
+    }
+
+    /// Secrets referenced by any actor's initial state, in declaration order.
+    /// Secrets may also be declared on the command line, so an undeclared
+    /// reference is only an error once bootstrap needs its value.
+    pub fn referenced_secrets(&self) -> Result<Vec<&str>> {
+        let mut names: Vec<&str> = Vec::new();
+        for (actor, spec) in &self.actors {
+            for reference in spec.references()? {
+                if let Some(secret) = reference.strip_prefix("secrets.") {
+                    let name = self
+                        .secrets
+                        .get_key_value(secret)
+                        .map(|(name, _)| name.as_str())
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Actor `{}` references undeclared secret `{}`; add [secrets.{}] to the profile or pass --secret {}=<SOURCE>",
+                                actor,
+                                secret,
+                                secret,
+                                secret
+                            )
+                        })?;
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// Explicit `depends_on` entries plus every actor referenced from the
+    /// initial state template.
+    pub fn dependencies(&self, actor: &str) -> Result<Vec<String>> {
+        let spec = &self.actors[actor];
+        let mut dependencies = spec.depends_on.clone();
+        for reference in spec.references()? {
+            if let Some(id_ref) = reference.strip_prefix("actors.") {
+                let dependency = id_ref.strip_suffix(".id").unwrap_or(id_ref).to_string();
+                if !dependencies.contains(&dependency) {
+                    dependencies.push(dependency);
+                }
+            }
+        }
+        Ok(dependencies)
+    }
+
+    /// Actors in an order where every actor comes after its dependencies,
+    /// keeping declaration order wherever the graph allows it.
+    pub fn start_order(&self) -> Result<Vec<&str>> {
+        let dependencies = self
+            .actors
+            .keys()
+            .map(|name| self.dependencies(name))
+            .collect::<Result<Vec<_>>>()?;
+        let mut order: Vec<&str> = Vec::with_capacity(self.actors.len());
+
+        while order.len() < self.actors.len() {
+            let next = self
+                .actors
+                .keys()
+                .zip(&dependencies)
+                .find_map(|(name, deps)| {
+                    let ready = !order.contains(&name.as_str())
+                        && deps
+                            .iter()
+                            .all(|dependency| order.contains(&dependency.as_str()));
+                    ready.then_some(name)
+                });
+            match next {
+                Some(name) => order.push(name),
+                None => {
+                    let remaining: Vec<&str> = self
+                        .actors
+                        .keys()
+                        .map(String::as_str)
+                        .filter(|name| !order.contains(name))
+                        .collect();
+                    anyhow::bail!("Dependency cycle between actors: {}", remaining.join(", "));
+                }
+            }
+        }
+
+        Ok(order)
+    }
+}
+
+impl ActorSpec {
+    /// Names of every `${...}` placeholder in the initial state template.
+    fn references(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        if let Some(template) = &self.initial_state {
+            walk_strings(template, &mut |s| {
+                scan(s, &mut |name| {
+                    names.push(name.to_string());
+                    Ok(String::new())
+                })
+                .map(|_| ())
+            })?;
+        }
+        Ok(names)
+    }
+
+    pub fn manifest_str(&self) -> String {
+        self.manifest.to_string_lossy().into_owned()
+    }
+
+    /// Renders the initial state template to the bytes sent with `StartActor`.
+    pub fn render_initial_state(
+        &self,
+        actor: &str,
+        placeholders: &Placeholders,
+    ) -> Result<Option<Vec<u8>>> {
+        match &self.initial_state {
+            Some(template) => {
+                let state = placeholders
+                    .render(template)
+                    .with_context(|| format!("Invalid initial_state for actor `{}`", actor))?;
+                Ok(Some(serde_json::to_vec(&state)?))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Fails unless `reference` names something a template can refer to:
+/// `stores.<name>`, `actors.<name>.id`, `secrets.<name>` or `env.<VAR>`.
+fn check_reference(reference: &str) -> Result<()> {
+    let (namespace, rest) = reference.split_once('.').unwrap_or((reference, ""));
+    let expected = match namespace {
+        "stores" => "stores.<name>",
+        "actors" => "actors.<name>.id",
+        "secrets" => "secrets.<name>",
+        "env" => "env.<VAR>",
+        _ => anyhow::bail!(
+            "Unknown placeholder ${{{}}}; use stores, actors, secrets or env",
+            reference
+        ),
+    };
+    let valid = match namespace {
+        "actors" => rest
+            .strip_suffix(".id")
+            .is_some_and(|name| !name.is_empty() && !name.contains('.')),
+        _ => !rest.is_empty(),
+    };
+    if !valid {
+        anyhow::bail!(
+            "Invalid placeholder ${{{}}}; expected ${{{}}}",
+            reference,
+            expected
+        );
+    }
+    Ok(())
+}
+
+fn walk_strings(value: &Value, f: &mut impl FnMut(&str) -> Result<()>) -> Result<()> {
+    match value {
+        Value::String(s) => f(s),
+        Value::Array(items) => items.iter().try_for_each(|item| walk_strings(item, f)),
+        Value::Object(map) => map.values().try_for_each(|item| walk_strings(item, f)),
+        _ => Ok(()),
+    }
+}
+
+fn scan(input: &str, lookup: &mut impl FnMut(&str) -> Result<String>) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("Unterminated placeholder in \"{}\"", input))?;
+        output.push_str(&lookup(&rest[start + 2..start + end])?);
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// Values available to `${...}` references in initial state templates.
+#[derive(Debug, Default)]
+pub struct Placeholders {
+    values: HashMap<String, String>,
+}
+
+impl Placeholders {
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    pub fn render(&self, template: &Value) -> Result<Value> {
+        Ok(match template {
+            Value::String(s) => Value::String(scan(s, &mut |name| self.lookup(name))?),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.render(item))
+                    .collect::<Result<_>>()?,
+            ),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| Ok((key.clone(), self.render(value)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            other => other.clone(),
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Result<String> {
+        if let Some(var) = name.strip_prefix("env.") {
+            return env::var(var)
+                .map_err(|_| anyhow::anyhow!("{} environment variable not set", var));
+        }
+        self.values
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown placeholder ${{{}}}", name))
+    }
+}
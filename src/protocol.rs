@@ -1,12 +1,37 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Version of the management protocol this client speaks.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Commands every server understands, including those that predate the
+/// handshake.
+pub const LEGACY_COMMANDS: &[&str] = &[
+    "StartActor",
+    "StopActor",
+    "NewStore",
+    "RequestActorMessage",
+    "OpenChannel",
+    "SendOnChannel",
+    "CloseChannel",
+];
 
 // Theater Server Management Commands
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ManagementCommand {
+    /// Sent first on every connection; answered with `ServerInfo`.
+    Handshake {
+        protocol_version: u32,
+        client: String,
+    },
     StartActor {
         manifest: String,
         initial_state: Option<Vec<u8>>,
     },
+    StopActor {
+        id: String,
+    },
     NewStore {},
     RequestActorMessage {
         id: String,
@@ -23,16 +48,143 @@ pub enum ManagementCommand {
     CloseChannel {
         channel_id: String,
     },
+    RestartActor {
+        id: String,
+    },
+    ListActors {},
+    GetActorState {
+        id: String,
+    },
+    /// The actor's event chain.
+    GetActorEvents {
+        id: String,
+    },
+    /// Fire-and-forget counterpart of `RequestActorMessage`.
+    SendActorMessage {
+        id: String,
+        data: Vec<u8>,
+    },
+    /// Streams the actor's events to this connection as `ActorEvent`s.
+    SubscribeToActor {
+        id: String,
+    },
+    UnsubscribeFromActor {
+        id: String,
+        subscription_id: String,
+    },
 }
 
-#[derive(Debug, Deserialize)]
+impl ManagementCommand {
+    /// Every command this client can send, by variant name.
+    pub const NAMES: &'static [&'static str] = &[
+        "Handshake",
+        "StartActor",
+        "StopActor",
+        "NewStore",
+        "RequestActorMessage",
+        "OpenChannel",
+        "SendOnChannel",
+        "CloseChannel",
+        "RestartActor",
+        "ListActors",
+        "GetActorState",
+        "GetActorEvents",
+        "SendActorMessage",
+        "SubscribeToActor",
+        "UnsubscribeFromActor",
+    ];
+
+    /// The variant name, as the command is tagged on the wire.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ManagementCommand::Handshake { .. } => "Handshake",
+            ManagementCommand::StartActor { .. } => "StartActor",
+            ManagementCommand::StopActor { .. } => "StopActor",
+            ManagementCommand::NewStore {} => "NewStore",
+            ManagementCommand::RequestActorMessage { .. } => "RequestActorMessage",
+            ManagementCommand::OpenChannel { .. } => "OpenChannel",
+            ManagementCommand::SendOnChannel { .. } => "SendOnChannel",
+            ManagementCommand::CloseChannel { .. } => "CloseChannel",
+            ManagementCommand::RestartActor { .. } => "RestartActor",
+            ManagementCommand::ListActors {} => "ListActors",
+            ManagementCommand::GetActorState { .. } => "GetActorState",
+            ManagementCommand::GetActorEvents { .. } => "GetActorEvents",
+            ManagementCommand::SendActorMessage { .. } => "SendActorMessage",
+            ManagementCommand::SubscribeToActor { .. } => "SubscribeToActor",
+            ManagementCommand::UnsubscribeFromActor { .. } => "UnsubscribeFromActor",
+        }
+    }
+
+    /// JSON form of the command with its byte payloads decoded to JSON (or
+    /// text) where possible, for display.
+    pub fn to_display_json(&self) -> Value {
+        display_json(self)
+    }
+}
+
+fn display_json(message: &impl Serialize) -> Value {
+    let mut value = serde_json::to_value(message).unwrap_or(Value::Null);
+    decode_payloads(&mut value);
+    value
+}
+
+/// Fields that carry `Vec<u8>` payloads in the management protocol.
+const PAYLOAD_FIELDS: &[&str] = &[
+    "initial_state",
+    "data",
+    "message",
+    "initial_message",
+    "state",
+];
+
+fn decode_payloads(value: &mut Value) {
+    if let Value::Array(items) = value {
+        items.iter_mut().for_each(decode_payloads);
+    }
+    if let Value::Object(map) = value {
+        for (key, field) in map.iter_mut() {
+            if PAYLOAD_FIELDS.contains(&key.as_str()) {
+                if let Some(bytes) = payload_bytes(field) {
+                    *field = serde_json::from_slice(&bytes).unwrap_or_else(|_| {
+                        String::from_utf8(bytes.clone())
+                            .map(Value::String)
+                            .unwrap_or_else(|_| field.clone())
+                    });
+                }
+            } else {
+                decode_payloads(field);
+            }
+        }
+    }
+}
+
+fn payload_bytes(value: &Value) -> Option<Vec<u8>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+        .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum ManagementResponse {
+    /// The server's protocol version, the oldest client version it still
+    /// accepts, and the commands it supports.
+    ServerInfo {
+        protocol_version: u32,
+        min_protocol_version: u32,
+        commands: Vec<String>,
+    },
     StoreCreated {
         store_id: String,
     },
     ActorStarted {
         id: String,
     },
+    ActorStopped {
+        id: String,
+    },
     RequestedMessage {
         id: String,
         message: Vec<u8>,
@@ -52,9 +204,110 @@ pub enum ManagementResponse {
     ChannelClosed {
         channel_id: String,
     },
+    ActorRestarted {
+        id: String,
+    },
+    ActorList {
+        actors: Vec<String>,
+    },
+    ActorState {
+        id: String,
+        state: Option<Vec<u8>>,
+    },
+    /// Chain events are passed through as JSON.
+    ActorEvents {
+        id: String,
+        events: Vec<Value>,
+    },
+    SentMessage {
+        id: String,
+    },
+    Subscribed {
+        id: String,
+        subscription_id: String,
+    },
+    Unsubscribed {
+        id: String,
+    },
+    /// An event of a subscribed actor; never the answer to a request.
+    ActorEvent {
+        id: String,
+        event: Value,
+    },
     Error {
         message: String,
     },
+    /// A response this client does not understand, most likely from a newer
+    /// server, kept as its variant name and raw JSON payload.
+    #[serde(skip)]
+    Unknown {
+        variant: String,
+        payload: Value,
+    },
+}
+
+impl Serialize for ManagementResponse {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ManagementResponse::Unknown { variant, payload } => {
+                serialize_unknown(variant, payload, serializer)
+            }
+            known => ManagementResponse::serialize(known, serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ManagementResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        match ManagementResponse::deserialize(&value) {
+            Ok(known) => Ok(known),
+            Err(e) => {
+                let (variant, payload) = split_unknown(value, ManagementResponse::VARIANTS)
+                    .ok_or_else(|| serde::de::Error::custom(e))?;
+                Ok(ManagementResponse::Unknown { variant, payload })
+            }
+        }
+    }
+}
+
+impl ManagementResponse {
+    /// Every response this client understands, by variant name.
+    const VARIANTS: &'static [&'static str] = &[
+        "ServerInfo",
+        "StoreCreated",
+        "ActorStarted",
+        "ActorStopped",
+        "RequestedMessage",
+        "ChannelOpened",
+        "MessageSent",
+        "ChannelMessage",
+        "ChannelClosed",
+        "ActorRestarted",
+        "ActorList",
+        "ActorState",
+        "ActorEvents",
+        "SentMessage",
+        "Subscribed",
+        "Unsubscribed",
+        "ActorEvent",
+        "Error",
+    ];
+
+    /// Decodes a frame received from the server, counting it if it is a
+    /// response this client does not understand.
+    pub fn decode(bytes: &[u8]) -> serde_json::Result<Self> {
+        let response = serde_json::from_slice(bytes)?;
+        if matches!(response, ManagementResponse::Unknown { .. }) {
+            UNKNOWN_VARIANTS.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(response)
+    }
+
+    /// JSON form of the response with its byte payloads decoded, for display.
+    pub fn to_display_json(&self) -> Value {
+        display_json(self)
+    }
 }
 
 // Channel participant types
@@ -65,7 +318,7 @@ pub enum ChannelParticipant {
 }
 
 // Frontend Commands (for REPL)
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FrontendCommand {
     StartActor,
     StopActor,
@@ -75,7 +328,7 @@ pub enum FrontendCommand {
     Disconnect,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum OperationType {
     #[serde(rename = "Start")]
     Start,
@@ -114,38 +367,38 @@ pub enum BuildEventType {
     FileExtracted,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BuildEventDetails {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub level: Option<String>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub percent_complete: Option<f32>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status: Option<String>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub command: Option<String>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub args: Option<Vec<String>>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stdout: Option<String>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stderr: Option<String>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub success: Option<bool>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub wasm_path: Option<String>,
-    
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub wasm_hash: Option<String>,
 }
@@ -157,7 +410,9 @@ pub struct OperationSummary {
     pub description: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
+#[allow(clippy::large_enum_variant)]
 pub enum FrontendMessage {
     Status {
         child_running: bool,
@@ -198,5 +453,125 @@ pub enum FrontendMessage {
         message: String,
         details: BuildEventDetails,
     },
+    /// A message this client does not understand, most likely from a newer
+    /// manager, kept as its variant name and raw JSON payload.
+    #[serde(skip)]
+    Unknown {
+        variant: String,
+        payload: Value,
+    },
 }
 
+impl FrontendMessage {
+    /// Every message this client understands, by variant name.
+    const VARIANTS: &'static [&'static str] = &[
+        "Status",
+        "OperationStarted",
+        "OperationCompleted",
+        "OperationProgress",
+        "ChildStarted",
+        "ChildStopped",
+        "Log",
+        "Error",
+        "BuildEvent",
+    ];
+
+    /// The variant name, as the message is tagged on the wire.
+    pub fn name(&self) -> &str {
+        match self {
+            FrontendMessage::Status { .. } => "Status",
+            FrontendMessage::OperationStarted { .. } => "OperationStarted",
+            FrontendMessage::OperationCompleted { .. } => "OperationCompleted",
+            FrontendMessage::OperationProgress { .. } => "OperationProgress",
+            FrontendMessage::ChildStarted { .. } => "ChildStarted",
+            FrontendMessage::ChildStopped { .. } => "ChildStopped",
+            FrontendMessage::Log { .. } => "Log",
+            FrontendMessage::Error { .. } => "Error",
+            FrontendMessage::BuildEvent { .. } => "BuildEvent",
+            FrontendMessage::Unknown { variant, .. } => variant,
+        }
+    }
+
+    /// The operation the message is about, if any.
+    pub fn operation_id(&self) -> Option<&str> {
+        match self {
+            FrontendMessage::OperationStarted { operation_id, .. }
+            | FrontendMessage::OperationCompleted { operation_id, .. }
+            | FrontendMessage::OperationProgress { operation_id, .. }
+            | FrontendMessage::BuildEvent { operation_id, .. } => Some(operation_id),
+            _ => None,
+        }
+    }
+
+    /// Decodes a channel message received from the actor. Anything that is
+    /// not a message this client understands still comes back as `Unknown`,
+    /// with the text as its payload, and is counted.
+    pub fn decode(bytes: &[u8]) -> Self {
+        let msg = serde_json::from_slice(bytes).unwrap_or_else(|_| FrontendMessage::Unknown {
+            variant: "(not a frontend message)".to_string(),
+            payload: Value::String(String::from_utf8_lossy(bytes).into_owned()),
+        });
+        if matches!(msg, FrontendMessage::Unknown { .. }) {
+            UNKNOWN_VARIANTS.fetch_add(1, Ordering::Relaxed);
+        }
+        msg
+    }
+}
+
+impl Serialize for FrontendMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            FrontendMessage::Unknown { variant, payload } => {
+                serialize_unknown(variant, payload, serializer)
+            }
+            known => FrontendMessage::serialize(known, serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FrontendMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        match FrontendMessage::deserialize(&value) {
+            Ok(known) => Ok(known),
+            Err(e) => {
+                let (variant, payload) = split_unknown(value, FrontendMessage::VARIANTS)
+                    .ok_or_else(|| serde::de::Error::custom(e))?;
+                Ok(FrontendMessage::Unknown { variant, payload })
+            }
+        }
+    }
+}
+
+/// Number of responses and frontend messages received as `Unknown` so far.
+static UNKNOWN_VARIANTS: AtomicU64 = AtomicU64::new(0);
+
+/// How many messages from the server this client has not understood.
+pub fn unknown_variants_seen() -> u64 {
+    UNKNOWN_VARIANTS.load(Ordering::Relaxed)
+}
+
+/// Splits an externally tagged value into its variant name and payload,
+/// unless the tag is one of `known`: a known variant that failed to decode
+/// is malformed, not unknown.
+fn split_unknown(value: Value, known: &[&str]) -> Option<(String, Value)> {
+    let (variant, payload) = match value {
+        Value::String(variant) => (variant, Value::Null),
+        Value::Object(map) if map.len() == 1 => map.into_iter().next()?,
+        _ => return None,
+    };
+    (!known.contains(&variant.as_str())).then_some((variant, payload))
+}
+
+/// Writes an unknown variant back out in the form it arrived in.
+fn serialize_unknown<S: Serializer>(
+    variant: &str,
+    payload: &Value,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let value = match payload {
+        Value::Null => Value::String(variant.to_string()),
+        payload => serde_json::json!({ variant: payload }),
+    };
+    value.serialize(serializer)
+}
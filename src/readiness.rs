@@ -0,0 +1,167 @@
+use crate::client::ManagementClient;
+use crate::output;
+use crate::say;
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How to tell that a freshly started actor is ready: send it `action` and
+/// wait until the reply's `field` equals `expect`, retrying with exponential
+/// backoff until `retries` or `deadline_secs` runs out. Every actor started
+/// is probed; one that does not answer opts out with `enabled = false`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ReadinessProbe {
+    pub enabled: bool,
+    pub action: String,
+    pub params: Value,
+    pub field: String,
+    pub expect: Value,
+    pub retries: u32,
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub deadline_secs: u64,
+}
+
+impl Default for ReadinessProbe {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            action: "get-info".to_string(),
+            params: json!([]),
+            field: "status".to_string(),
+            expect: json!("success"),
+            retries: 5,
+            backoff_ms: 250,
+            max_backoff_ms: 4000,
+            deadline_secs: 30,
+        }
+    }
+}
+
+/// An actor's own `readiness` table: the fields it sets replace those of
+/// the profile's `[readiness]`, the rest are inherited.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReadinessOverride {
+    pub enabled: Option<bool>,
+    pub action: Option<String>,
+    pub params: Option<Value>,
+    pub field: Option<String>,
+    pub expect: Option<Value>,
+    pub retries: Option<u32>,
+    pub backoff_ms: Option<u64>,
+    pub max_backoff_ms: Option<u64>,
+    pub deadline_secs: Option<u64>,
+}
+
+impl ReadinessProbe {
+    /// This probe with the fields `actor` sets replaced.
+    pub fn overridden(&self, actor: &ReadinessOverride) -> Self {
+        let base = self.clone();
+        Self {
+            enabled: actor.enabled.unwrap_or(base.enabled),
+            action: actor.action.clone().unwrap_or(base.action),
+            params: actor.params.clone().unwrap_or(base.params),
+            field: actor.field.clone().unwrap_or(base.field),
+            expect: actor.expect.clone().unwrap_or(base.expect),
+            retries: actor.retries.unwrap_or(base.retries),
+            backoff_ms: actor.backoff_ms.unwrap_or(base.backoff_ms),
+            max_backoff_ms: actor.max_backoff_ms.unwrap_or(base.max_backoff_ms),
+            deadline_secs: actor.deadline_secs.unwrap_or(base.deadline_secs),
+        }
+    }
+
+    /// Sends the probe once and checks the reply, giving up after `timeout`.
+    pub async fn check(
+        &self,
+        client: &mut ManagementClient,
+        actor_id: &str,
+        timeout: Duration,
+    ) -> Result<()> {
+        let request = json!({
+            "action": self.action,
+            "params": self.params,
+        })
+        .to_string()
+        .into_bytes();
+
+        let message = client
+            .request_actor_message_within(actor_id, request, timeout)
+            .await?;
+        let response_str = String::from_utf8(message)?;
+        let response_json: Value = serde_json::from_str(&response_str)?;
+
+        if response_json.get(&self.field) == Some(&self.expect) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "expected {} = {}, got {}",
+                self.field,
+                self.expect,
+                response_str
+            ))
+        }
+    }
+
+    /// Probes until the actor is ready, printing progress while waiting.
+    pub async fn wait_ready(
+        &self,
+        client: &mut ManagementClient,
+        name: &str,
+        actor_id: &str,
+    ) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if client.is_dry_run() {
+            // Show the probe that would be sent; there is nothing to wait for
+            let _ = self
+                .check(client, actor_id, Duration::from_secs(self.deadline_secs))
+                .await;
+            return Ok(());
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(self.deadline_secs);
+        let attempts = self.retries + 1;
+        let mut backoff = Duration::from_millis(self.backoff_ms);
+
+        say!("Waiting for {} to become ready ({})...", name, self.action);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let error = match self.check(client, actor_id, remaining).await {
+                Ok(()) => {
+                    say!("  {} is ready", name);
+                    output::emit("actor_ready", json!({ "name": name, "id": actor_id }));
+                    return Ok(());
+                }
+                Err(e) => e,
+            };
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if attempt == attempts || remaining.is_zero() {
+                anyhow::bail!(
+                    "{} did not become ready after {} attempt(s): {}",
+                    name,
+                    attempt,
+                    error
+                );
+            }
+
+            let wait = backoff.min(remaining);
+            say!(
+                "  attempt {}/{} failed ({}), retrying in {:?}",
+                attempt,
+                attempts,
+                error,
+                wait
+            );
+            tokio::time::sleep(wait).await;
+            backoff = (backoff * 2).min(Duration::from_millis(self.max_backoff_ms));
+        }
+    }
+}
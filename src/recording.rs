@@ -0,0 +1,133 @@
+use crate::output;
+use crate::protocol::{ManagementCommand, ManagementResponse};
+use crate::secrets::Redactor;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// One line of a recording: a connection being opened, or a frame sent or
+/// received on it. Frames are stored with their byte payloads decoded to
+/// JSON where possible for reading, and as sent on the wire for replaying,
+/// both with secrets redacted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: String,
+    pub connection: u64,
+    pub direction: Direction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+    /// The frame as sent on the wire, byte payloads included byte for byte.
+    /// Every sent and received entry has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Connected,
+    Sent,
+    Received,
+}
+
+static RECORDING: OnceLock<Mutex<File>> = OnceLock::new();
+static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(1);
+static PROFILE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Appends every frame of every connection made from now on to `path`.
+pub fn start(path: &Path) -> Result<()> {
+    let file = File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open recording file {}", path.display()))?;
+    RECORDING
+        .set(Mutex::new(file))
+        .map_err(|_| anyhow::anyhow!("Already recording"))
+}
+
+/// Records the manifest of each `StartActor` under `dir`, the profile's
+/// directory, relative to it, so that a bootstrap recorded in one checkout
+/// replays against the same profile in another.
+pub fn manifests_relative_to(dir: &Path) {
+    let _ = PROFILE_DIR.set(dir.to_path_buf());
+}
+
+/// A fresh ID for a connection, recording that it was opened to `address`.
+pub(crate) fn connected(address: &str) -> u64 {
+    let connection = NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed);
+    write(Entry {
+        address: Some(address.to_string()),
+        ..entry(connection, Direction::Connected)
+    });
+    connection
+}
+
+pub(crate) fn sent(connection: u64, redactor: &Redactor, command: &ManagementCommand) {
+    if RECORDING.get().is_some() {
+        write(Entry {
+            command: Some(redactor.redact_value(&portable(command.to_display_json()))),
+            frame: serde_json::to_value(command)
+                .ok()
+                .map(|frame| redactor.redact_frame(&frame)),
+            ..entry(connection, Direction::Sent)
+        });
+    }
+}
+
+pub(crate) fn received(connection: u64, redactor: &Redactor, response: &ManagementResponse) {
+    if RECORDING.get().is_some() {
+        write(Entry {
+            response: Some(redactor.redact_value(&response.to_display_json())),
+            frame: serde_json::to_value(response)
+                .ok()
+                .map(|frame| redactor.redact_frame(&frame)),
+            ..entry(connection, Direction::Received)
+        });
+    }
+}
+
+/// `command` with a manifest under the profile's directory made relative.
+fn portable(mut command: Value) -> Value {
+    let (Some(dir), Some(Value::String(manifest))) = (
+        PROFILE_DIR.get(),
+        command.pointer_mut("/StartActor/manifest"),
+    ) else {
+        return command;
+    };
+    if let Ok(relative) = Path::new(manifest.as_str()).strip_prefix(dir) {
+        *manifest = relative.to_string_lossy().into_owned();
+    }
+    command
+}
+
+fn entry(connection: u64, direction: Direction) -> Entry {
+    Entry {
+        timestamp: output::timestamp(),
+        connection,
+        direction,
+        address: None,
+        command: None,
+        response: None,
+        frame: None,
+    }
+}
+
+fn write(entry: Entry) {
+    let Some(file) = RECORDING.get() else {
+        return;
+    };
+    if let Ok(line) = serde_json::to_string(&entry) {
+        // A failing recording should not take the session down with it
+        let _ = writeln!(file.lock().unwrap(), "{}", line);
+    }
+}
@@ -1,169 +1,741 @@
+use crate::client::{ManagementClient, ServerError};
+use crate::operations::{render_operation, render_operations, render_summary, Tracker};
+use crate::output;
 use crate::protocol::*;
-use anyhow::Result;
-use bytes::Bytes;
-use futures::{SinkExt, StreamExt};
-use tokio::net::TcpStream;
-use tokio::sync::mpsc;
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use crate::say;
+use crate::script::Script;
+use crate::secrets::Redactor;
+use crate::teardown;
+use crate::transport::Transport;
+use anyhow::{Context, Result};
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Behavior, Config, Editor, Helper};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::io::IsTerminal;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// How long [`ChannelRepl::close`] waits for the server to close the channel.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Something for a [`ChannelRepl`] to send: a command for the actor on the
+/// channel, or a management command for the server itself.
+#[derive(Debug)]
+pub enum ReplCommand {
+    Frontend(FrontendCommand),
+    Management(ManagementCommand),
+}
+
+impl ReplCommand {
+    fn is_disconnect(&self) -> bool {
+        matches!(self, ReplCommand::Frontend(FrontendCommand::Disconnect))
+    }
+}
+
+/// What a [`ChannelRepl`] hands to its reader: messages from the actor,
+/// answers to management commands, and notices about the connection.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum ReplEvent {
+    Message(FrontendMessage),
+    /// The answer to a management command, an `ActorEvent` of a subscribed
+    /// actor, or a response this client does not recognize.
+    Management(ManagementResponse),
+    /// A management command the server rejected or does not support.
+    Failed {
+        command: &'static str,
+        error: String,
+    },
+    /// The connection dropped; commands are held until it is back.
+    ConnectionLost {
+        error: String,
+    },
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+    },
+    /// The channel is open again on a new connection.
+    Reconnected {
+        channel_id: String,
+    },
+    /// Every reconnection attempt failed; the session is over.
+    GaveUp {
+        attempts: u32,
+        error: String,
+    },
+}
+
+/// How a [`ChannelRepl`] gets its channel back after losing the connection:
+/// up to `attempts` tries, waiting `backoff` before the first and doubling
+/// up to `max_backoff`, each try allowed `timeout` to reopen the channel.
+#[derive(Debug, Clone)]
+pub struct Reconnect {
+    pub attempts: u32,
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            attempts: 10,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
 
 pub struct ChannelRepl {
-    command_tx: mpsc::Sender<FrontendCommand>,
-    message_rx: mpsc::Receiver<FrontendMessage>,
+    command_tx: mpsc::Sender<ReplCommand>,
+    event_rx: mpsc::Receiver<ReplEvent>,
+    tracker: Option<Tracker>,
+}
+
+/// The actor a channel is opened to, kept to reopen it after a reconnect.
+struct Target {
+    address: String,
+    transport: Transport,
+    actor_id: String,
+    reconnect: Reconnect,
 }
 
 impl ChannelRepl {
-    pub async fn new(addr: &str, actor_id: &str) -> Result<Self> {
-        let stream = TcpStream::connect(addr).await?;
+    pub async fn new(addr: &str, transport: &Transport, actor_id: &str) -> Result<Self> {
+        Self::with_reconnect(addr, transport, actor_id, Reconnect::default()).await
+    }
 
-        let mut codec = LengthDelimitedCodec::new();
-        codec.set_max_frame_length(32 * 1024 * 1024);
-        let framed = Framed::new(stream, codec);
+    pub async fn with_reconnect(
+        addr: &str,
+        transport: &Transport,
+        actor_id: &str,
+        reconnect: Reconnect,
+    ) -> Result<Self> {
+        // Open the channel up front so a missing or unresponsive actor is
+        // reported to the caller rather than from the background task
+        let (client, channel_id) = open_channel(addr, transport, actor_id).await?;
 
-        let (command_tx, mut command_rx) = mpsc::channel::<FrontendCommand>(32);
-        let (message_tx, message_rx) = mpsc::channel::<FrontendMessage>(32);
+        let (command_tx, mut command_rx) = mpsc::channel::<ReplCommand>(32);
+        let (event_tx, event_rx) = mpsc::channel::<ReplEvent>(32);
 
         // Start connection handler task
-        let actor_id_clone = actor_id.to_string();
+        let target = Target {
+            address: addr.to_string(),
+            transport: transport.clone(),
+            actor_id: actor_id.to_string(),
+            reconnect,
+        };
         tokio::spawn(async move {
-            if let Err(e) =
-                handle_connection(framed, actor_id_clone, &mut command_rx, message_tx).await
-            {
-                eprintln!("Connection error: {}", e);
-            }
+            handle_connection(client, channel_id, target, &mut command_rx, event_tx).await;
         });
 
         Ok(Self {
             command_tx,
-            message_rx,
+            event_rx,
+            tracker: None,
         })
     }
+
+    /// Sends a command to the actor on the channel.
+    pub async fn send(&self, command: FrontendCommand) -> Result<()> {
+        self.submit(ReplCommand::Frontend(command)).await
+    }
+
+    /// Sends a management command to the server; its answer arrives as a
+    /// [`ReplEvent::Management`] or [`ReplEvent::Failed`].
+    pub async fn send_management(&self, command: ManagementCommand) -> Result<()> {
+        self.submit(ReplCommand::Management(command)).await
+    }
+
+    /// Closes the channel, waiting up to [`CLOSE_TIMEOUT`] for the server to
+    /// confirm it so a process about to exit does not leave it open. Events
+    /// still arriving are dropped.
+    pub async fn close(mut self) {
+        if self.send(FrontendCommand::Disconnect).await.is_err() {
+            return;
+        }
+        // The connection task drops its end of the events once the channel
+        // is closed
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, async {
+            while self.recv().await.is_some() {}
+        })
+        .await;
+    }
+
+    pub async fn submit(&self, command: ReplCommand) -> Result<()> {
+        self.command_tx
+            .send(command)
+            .await
+            .map_err(|_| anyhow::anyhow!("Channel closed"))
+    }
+
+    /// Next event, or `None` once the channel is closed for good.
+    pub async fn recv(&mut self) -> Option<ReplEvent> {
+        let event = self.event_rx.recv().await;
+        if let Some(tracker) = &self.tracker {
+            match &event {
+                Some(ReplEvent::Message(msg)) => tracker.record(msg),
+                Some(_) => {}
+                None => tracker.close(),
+            }
+        }
+        event
+    }
+
+    /// Records every message [`ChannelRepl::recv`] hands out in `tracker`
+    /// from now on, closing it along with the channel.
+    pub fn track(&mut self, tracker: Tracker) {
+        self.tracker = Some(tracker);
+    }
+
+    /// Requests the actor's status and waits for it to answer on the channel.
+    pub async fn ping(&mut self, timeout: Duration) -> Result<FrontendMessage> {
+        self.send(FrontendCommand::GetStatus).await?;
+
+        tokio::time::timeout(timeout, async {
+            loop {
+                match self.recv().await {
+                    Some(ReplEvent::Message(msg @ FrontendMessage::Status { .. })) => {
+                        return Ok(msg)
+                    }
+                    Some(_) => continue,
+                    None => anyhow::bail!("Channel closed before the actor answered"),
+                }
+            }
+        })
+        .await
+        .map_err(|_| anyhow::anyhow!("Actor did not answer within {:?}", timeout))?
+    }
 }
 
-async fn handle_connection(
-    mut framed: Framed<TcpStream, LengthDelimitedCodec>,
-    actor_id: String,
-    command_rx: &mut mpsc::Receiver<FrontendCommand>,
-    message_tx: mpsc::Sender<FrontendMessage>,
-) -> Result<()> {
-    // Open the channel first
+async fn open_channel(
+    address: &str,
+    transport: &Transport,
+    actor_id: &str,
+) -> Result<(ManagementClient, String)> {
+    let mut client = ManagementClient::connect(address, transport).await?;
     let initial_message = serde_json::json!({
         "client_type": "frontend"
     });
+    let channel_id = client
+        .open_channel(
+            ChannelParticipant::Actor(actor_id.to_string()),
+            serde_json::to_vec(&initial_message)?,
+        )
+        .await
+        .context("Failed to open channel")?;
+    Ok((client, channel_id))
+}
 
-    let cmd = ManagementCommand::OpenChannel {
-        actor_id: ChannelParticipant::Actor(actor_id),
-        initial_message: serde_json::to_vec(&initial_message)?,
-    };
-
-    let cmd_bytes = serde_json::to_vec(&cmd)?;
-    framed.send(Bytes::from(cmd_bytes)).await?;
-
-    let response = framed
-        .next()
+/// Relays commands and messages until the user disconnects or the actor
+/// closes the channel, reconnecting whenever the connection is lost.
+async fn handle_connection(
+    mut client: ManagementClient,
+    mut channel_id: String,
+    target: Target,
+    command_rx: &mut mpsc::Receiver<ReplCommand>,
+    event_tx: mpsc::Sender<ReplEvent>,
+) {
+    let mut pending = VecDeque::new();
+    // Actors subscribed to on this connection, to subscribe to again on the next
+    let mut subscriptions = BTreeSet::new();
+    loop {
+        let error = match relay(
+            &mut client,
+            &channel_id,
+            command_rx,
+            &event_tx,
+            &mut pending,
+            &mut subscriptions,
+        )
         .await
-        .ok_or_else(|| anyhow::anyhow!("Connection closed"))??;
+        {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+        let lost = ReplEvent::ConnectionLost {
+            error: format!("{:#}", error),
+        };
+        if event_tx.send(lost).await.is_err() {
+            return;
+        }
 
-    let response: ManagementResponse = serde_json::from_slice(&response)?;
+        match reconnect(&target, command_rx, &event_tx, &mut pending).await {
+            Some((new_client, new_channel_id)) => {
+                client = new_client;
+                channel_id = new_channel_id;
+            }
+            None => return,
+        }
+        // Subscriptions end with the connection that made them
+        for id in std::mem::take(&mut subscriptions).into_iter().rev() {
+            pending.push_front(ReplCommand::Management(
+                ManagementCommand::SubscribeToActor { id },
+            ));
+        }
+        // Whatever happened while we were away, the status tells
+        pending.push_front(ReplCommand::Frontend(FrontendCommand::GetStatus));
+    }
+}
 
-    let channel_id = match response {
-        ManagementResponse::ChannelOpened { channel_id, .. } => channel_id,
-        ManagementResponse::Error { message } => {
-            anyhow::bail!("Failed to open channel: {}", message)
+/// Sends the commands held in `pending`, then relays in both directions.
+/// Returns `Ok` when the session is over and an error when the connection
+/// was lost, with any command that did not make it back in `pending`.
+async fn relay(
+    client: &mut ManagementClient,
+    channel_id: &str,
+    command_rx: &mut mpsc::Receiver<ReplCommand>,
+    event_tx: &mpsc::Sender<ReplEvent>,
+    pending: &mut VecDeque<ReplCommand>,
+    subscriptions: &mut BTreeSet<String>,
+) -> Result<()> {
+    while let Some(command) = pending.pop_front() {
+        if send_command(client, channel_id, &command, event_tx, subscriptions).await? {
+            return Ok(());
         }
-        _ => anyhow::bail!("Unexpected response"),
-    };
+    }
 
-    // Now handle the message loop
     loop {
         tokio::select! {
-            Some(command) = command_rx.recv() => {
-                // Handle disconnect command
-                if matches!(command, FrontendCommand::Disconnect) {
-                    let close_cmd = ManagementCommand::CloseChannel {
-                        channel_id: channel_id.clone(),
-                    };
-                    let cmd_bytes = serde_json::to_vec(&close_cmd)?;
-                    framed.send(Bytes::from(cmd_bytes)).await?;
-                    break;
-                }
-
-                // Send the command
-                let cmd = ManagementCommand::SendOnChannel {
-                    channel_id: channel_id.clone(),
-                    message: serde_json::to_vec(&command)?,
+            command = command_rx.recv() => {
+                let Some(command) = command else {
+                    return Ok(());
                 };
-                let cmd_bytes = serde_json::to_vec(&cmd)?;
-                framed.send(Bytes::from(cmd_bytes)).await?;
+                match send_command(client, channel_id, &command, event_tx, subscriptions).await {
+                    Ok(true) => return Ok(()),
+                    Ok(false) => {}
+                    Err(e) => {
+                        pending.push_back(command);
+                        return Err(e);
+                    }
+                }
             }
-            result = framed.next() => {
-                match result {
-                    Some(Ok(bytes)) => {
-                        if let Ok(response) = serde_json::from_slice::<ManagementResponse>(&bytes) {
-                            match response {
-                                ManagementResponse::ChannelMessage { message, .. } => {
-                                    if let Ok(msg) = serde_json::from_slice::<FrontendMessage>(&message) {
-                                        if message_tx.send(msg).await.is_err() {
-                                            break;
-                                        }
-                                    }
-                                }
-                                ManagementResponse::ChannelClosed { .. } => break,
-                                _ => {}
-                            }
+            result = client.next_unsolicited() => {
+                match result? {
+                    Some(ManagementResponse::ChannelMessage { message, .. }) => {
+                        let msg = FrontendMessage::decode(&message);
+                        if event_tx.send(ReplEvent::Message(msg)).await.is_err() {
+                            return Ok(());
                         }
                     }
-                    Some(Err(e)) => {
-                        eprintln!("Frame error: {}", e);
-                        break;
+                    Some(ManagementResponse::ChannelClosed { .. }) => return Ok(()),
+                    Some(
+                        event @ (ManagementResponse::ActorEvent { .. }
+                        | ManagementResponse::Unknown { .. }),
+                    ) => {
+                        if event_tx.send(ReplEvent::Management(event)).await.is_err() {
+                            return Ok(());
+                        }
                     }
-                    None => break,
+                    Some(_) => {}
+                    None => anyhow::bail!("Connection closed by the server"),
                 }
             }
         }
     }
+}
 
-    Ok(())
+/// Sends one command, returning whether it ended the session. Answers to
+/// management commands, and rejections of any command, are passed on as
+/// events; only a lost connection is an error.
+async fn send_command(
+    client: &mut ManagementClient,
+    channel_id: &str,
+    command: &ReplCommand,
+    event_tx: &mpsc::Sender<ReplEvent>,
+    subscriptions: &mut BTreeSet<String>,
+) -> Result<bool> {
+    let command = match command {
+        ReplCommand::Frontend(FrontendCommand::Disconnect) => {
+            let closed = client.close_channel(channel_id).await;
+            report_rejection("CloseChannel", closed, event_tx).await?;
+            return Ok(true);
+        }
+        ReplCommand::Frontend(command) => {
+            let sent = client
+                .send_on_channel(channel_id, serde_json::to_vec(command)?)
+                .await;
+            report_rejection("SendOnChannel", sent, event_tx).await?;
+            return Ok(false);
+        }
+        ReplCommand::Management(command) => command,
+    };
+
+    let name = command.name();
+    let event = if !client.capabilities().supports(name) {
+        ReplEvent::Failed {
+            command: name,
+            error: format!("not supported by the server ({})", client.capabilities()),
+        }
+    } else {
+        match client.execute(command.clone()).await {
+            Ok(response) => {
+                match &response {
+                    ManagementResponse::Subscribed { id, .. } => subscriptions.insert(id.clone()),
+                    ManagementResponse::Unsubscribed { id } => subscriptions.remove(id),
+                    _ => false,
+                };
+                ReplEvent::Management(response)
+            }
+            Err(e) => match e.downcast::<ServerError>() {
+                Ok(e) => ReplEvent::Failed {
+                    command: name,
+                    error: e.message,
+                },
+                Err(e) => return Err(e),
+            },
+        }
+    };
+    let _ = event_tx.send(event).await;
+    Ok(false)
 }
 
-fn parse_command(line: &str) -> Result<FrontendCommand> {
-    match line.trim() {
-        "start" => Ok(FrontendCommand::StartActor),
-        "stop" => Ok(FrontendCommand::StopActor),
-        "build" => Ok(FrontendCommand::BuildActor),
-        "status" => Ok(FrontendCommand::GetStatus),
-        "exit" | "quit" => Ok(FrontendCommand::Disconnect),
-        cmd if cmd.starts_with("change ") => {
-            let desc = cmd.trim_start_matches("change ").to_string();
-            Ok(FrontendCommand::ChangeRequest { description: desc })
+/// Passes a command the server rejected on as [`ReplEvent::Failed`], leaving
+/// only other errors to end the connection.
+async fn report_rejection(
+    command: &'static str,
+    result: Result<()>,
+    event_tx: &mpsc::Sender<ReplEvent>,
+) -> Result<()> {
+    match result.map_err(|e| e.downcast::<ServerError>()) {
+        Ok(()) => Ok(()),
+        Err(Ok(e)) => {
+            let _ = event_tx
+                .send(ReplEvent::Failed {
+                    command,
+                    error: e.message,
+                })
+                .await;
+            Ok(())
         }
+        Err(Err(e)) => Err(e),
+    }
+}
+
+/// Reopens the channel with exponential backoff, holding commands typed in
+/// the meantime in `pending`. Gives up early if the user disconnects.
+async fn reconnect(
+    target: &Target,
+    command_rx: &mut mpsc::Receiver<ReplCommand>,
+    event_tx: &mpsc::Sender<ReplEvent>,
+    pending: &mut VecDeque<ReplCommand>,
+) -> Option<(ManagementClient, String)> {
+    let policy = &target.reconnect;
+    let mut delay = policy.backoff;
+    let mut error = String::new();
+
+    for attempt in 1..=policy.attempts {
+        event_tx
+            .send(ReplEvent::Reconnecting { attempt, delay })
+            .await
+            .ok()?;
+
+        let sleep = tokio::time::sleep(delay);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                command = command_rx.recv() => match command {
+                    Some(command) if command.is_disconnect() => return None,
+                    Some(command) => pending.push_back(command),
+                    None => return None,
+                },
+            }
+        }
+
+        let opened = tokio::time::timeout(
+            policy.timeout,
+            open_channel(&target.address, &target.transport, &target.actor_id),
+        )
+        .await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out after {:?}", policy.timeout)));
+        match opened {
+            Ok((client, channel_id)) => {
+                event_tx
+                    .send(ReplEvent::Reconnected {
+                        channel_id: channel_id.clone(),
+                    })
+                    .await
+                    .ok()?;
+                return Some((client, channel_id));
+            }
+            Err(e) => error = format!("{:#}", e),
+        }
+        delay = (delay * 2).min(policy.max_backoff);
+    }
+
+    let _ = event_tx
+        .send(ReplEvent::GaveUp {
+            attempts: policy.attempts,
+            error,
+        })
+        .await;
+    None
+}
+
+pub(crate) fn parse_command(line: &str) -> Result<ReplCommand> {
+    let line = line.trim();
+    let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let rest = rest.trim();
+    let frontend = |command| Ok(ReplCommand::Frontend(command));
+    let management = |command| Ok(ReplCommand::Management(command));
+
+    // Management commands name the actor they act on
+    let actor_id = || match rest.split_whitespace().next() {
+        Some(id) => Ok(id.to_string()),
+        None => anyhow::bail!("Usage: {} <actor-id> ...", word),
+    };
+    let actor_message = || -> Result<(String, Vec<u8>)> {
+        match rest.split_once(' ') {
+            Some((id, message)) => Ok((id.to_string(), message.trim().as_bytes().to_vec())),
+            None => anyhow::bail!("Usage: {} <actor-id> <message>", word),
+        }
+    };
+
+    match word {
+        "start" => frontend(FrontendCommand::StartActor),
+        "stop" => frontend(FrontendCommand::StopActor),
+        "build" => frontend(FrontendCommand::BuildActor),
+        "status" => frontend(FrontendCommand::GetStatus),
+        "exit" | "quit" => frontend(FrontendCommand::Disconnect),
+        "change" if !rest.is_empty() => frontend(FrontendCommand::ChangeRequest {
+            description: rest.to_string(),
+        }),
+        "actors" => management(ManagementCommand::ListActors {}),
+        "state" => management(ManagementCommand::GetActorState { id: actor_id()? }),
+        "events" => management(ManagementCommand::GetActorEvents { id: actor_id()? }),
+        "send" => {
+            let (id, data) = actor_message()?;
+            management(ManagementCommand::SendActorMessage { id, data })
+        }
+        "request" => {
+            let (id, data) = actor_message()?;
+            management(ManagementCommand::RequestActorMessage { id, data })
+        }
+        "restart-actor" => management(ManagementCommand::RestartActor { id: actor_id()? }),
+        "stop-actor" => management(ManagementCommand::StopActor { id: actor_id()? }),
+        "subscribe" => management(ManagementCommand::SubscribeToActor { id: actor_id()? }),
+        "unsubscribe" => match rest.split_whitespace().collect::<Vec<_>>()[..] {
+            [id, subscription_id] => management(ManagementCommand::UnsubscribeFromActor {
+                id: id.to_string(),
+                subscription_id: subscription_id.to_string(),
+            }),
+            _ => anyhow::bail!("Usage: unsubscribe <actor-id> <subscription-id>"),
+        },
         "help" => {
-            println!("\nAvailable commands:");
-            println!("  start         - Start the managed actor");
-            println!("  stop          - Stop the managed actor");
-            println!("  build         - Build the managed actor");
-            println!("  change <desc> - Submit a change request");
-            println!("  status        - Get current status");
-            println!("  help          - Show this help message");
-            println!("  exit/quit     - Exit the REPL");
-            println!("");
+            say!("\nAvailable commands:");
+            say!("  start         - Start the managed actor");
+            say!("  stop          - Stop the managed actor");
+            say!("  build         - Build the managed actor");
+            say!("  change <desc> - Submit a change request");
+            say!("  status        - Get current status");
+            say!("  ops           - List the operations seen so far");
+            say!("  show <op-id>  - Show an operation with its build log");
+            say!("  wait <op-id>  - Wait for an operation to complete");
+            say!("  help          - Show this help message");
+            say!("  exit/quit     - Exit the REPL");
+            say!("\nServer commands:");
+            say!("  actors                   - List running actors");
+            say!("  state <id>               - Show an actor's state");
+            say!("  events <id>              - Show an actor's event chain");
+            say!("  send <id> <msg>          - Send a message without waiting for a reply");
+            say!("  request <id> <msg>       - Send a message and show the reply");
+            say!("  restart-actor <id>       - Restart an actor");
+            say!("  stop-actor <id>          - Stop an actor");
+            say!("  subscribe <id>           - Stream an actor's events");
+            say!("  unsubscribe <id> <sub>   - Stop streaming an actor's events");
+            say!();
             anyhow::bail!("") // Use error to skip command sending
         }
         _ => anyhow::bail!("Unknown command. Type 'help' for available commands."),
     }
 }
 
-fn display_message(msg: &FrontendMessage, verbose: bool) {
+/// Prints a frontend message the way the REPL shows it.
+pub fn display_message(msg: &FrontendMessage) {
+    if output::is_json() {
+        output::emit("message", json!({ "message": msg }));
+    } else {
+        print!("{}", render_message(msg));
+    }
+}
+
+pub(crate) fn display_event(event: &ReplEvent, verbose: bool) {
+    if output::is_json() {
+        emit_event(event);
+        return;
+    }
+    match event {
+        ReplEvent::Message(msg) => {
+            // Skip BuildEvent messages if not in verbose mode, except for BuildComplete events
+            let should_display = match msg {
+                FrontendMessage::BuildEvent { event_type, .. } => {
+                    verbose || matches!(event_type, BuildEventType::BuildComplete)
+                }
+                _ => true,
+            };
+
+            if should_display {
+                display_message(msg);
+            }
+        }
+        ReplEvent::Management(response) => print!("{}", render_response(response)),
+        ReplEvent::Failed { command, error } => println!("Error: {} failed: {}", command, error),
+        ReplEvent::ConnectionLost { error } => {
+            println!("\nConnection lost: {}", error);
+            println!("Commands entered now will be sent once reconnected");
+        }
+        ReplEvent::Reconnecting { attempt, delay } => {
+            println!("Reconnecting in {:?} (attempt {})...", delay, attempt);
+        }
+        ReplEvent::Reconnected { channel_id } => {
+            println!("Reconnected, channel {} reopened", channel_id);
+        }
+        ReplEvent::GaveUp { attempts, error } => {
+            println!(
+                "Could not reconnect after {} attempt(s): {}",
+                attempts, error
+            );
+            println!("The session is over, type 'exit' to quit");
+        }
+    }
+}
+
+/// Writes an event as a JSON record; unlike the text output this includes
+/// every build event.
+fn emit_event(event: &ReplEvent) {
+    match event {
+        ReplEvent::Message(msg) => display_message(msg),
+        ReplEvent::Management(response) => output::emit(
+            "response",
+            json!({ "response": Redactor::global().redact_frame(&response.to_display_json()) }),
+        ),
+        ReplEvent::Failed { command, error } => output::emit(
+            "command_failed",
+            json!({ "command": command, "error": error }),
+        ),
+        ReplEvent::ConnectionLost { error } => {
+            output::emit("connection_lost", json!({ "error": error }))
+        }
+        ReplEvent::Reconnecting { attempt, delay } => output::emit(
+            "reconnecting",
+            json!({ "attempt": attempt, "delay_ms": delay.as_millis() as u64 }),
+        ),
+        ReplEvent::Reconnected { channel_id } => {
+            output::emit("reconnected", json!({ "channel_id": channel_id }))
+        }
+        ReplEvent::GaveUp { attempts, error } => {
+            output::emit("gave_up", json!({ "attempts": attempts, "error": error }))
+        }
+    }
+}
+
+/// Renders the answer to a management command the way the REPL shows it,
+/// with known secrets masked.
+pub fn render_response(response: &ManagementResponse) -> String {
+    let mut out = String::new();
+    write_response(&mut out, response).expect("writing to a String cannot fail");
+    Redactor::global().redact(&out)
+}
+
+fn write_response(out: &mut impl fmt::Write, response: &ManagementResponse) -> fmt::Result {
+    match response {
+        ManagementResponse::ActorList { actors } if actors.is_empty() => {
+            writeln!(out, "No actors running")?;
+        }
+        ManagementResponse::ActorList { actors } => {
+            writeln!(out, "Actors ({}):", actors.len())?;
+            for id in actors {
+                writeln!(out, "  {}", id)?;
+            }
+        }
+        ManagementResponse::ActorState { id, state: None } => {
+            writeln!(out, "Actor {} has no state", id)?;
+        }
+        ManagementResponse::ActorState {
+            id,
+            state: Some(state),
+        } => {
+            writeln!(out, "State of {}:", id)?;
+            write_payload(out, state)?;
+        }
+        ManagementResponse::ActorEvents { id, events } => {
+            writeln!(out, "Events of {} ({}):", id, events.len())?;
+            if let Some(events) = response.to_display_json()["ActorEvents"]["events"].as_array() {
+                for event in events {
+                    writeln!(out, "  {}", event)?;
+                }
+            }
+        }
+        ManagementResponse::RequestedMessage { id, message } => {
+            writeln!(out, "Reply from {}:", id)?;
+            write_payload(out, message)?;
+        }
+        ManagementResponse::SentMessage { id } => writeln!(out, "✓ Message sent to {}", id)?,
+        ManagementResponse::ActorRestarted { id } => writeln!(out, "✓ Actor {} restarted", id)?,
+        ManagementResponse::ActorStopped { id } => writeln!(out, "✓ Actor {} stopped", id)?,
+        ManagementResponse::Subscribed {
+            id,
+            subscription_id,
+        } => writeln!(
+            out,
+            "✓ Subscribed to {} (subscription {})",
+            id, subscription_id
+        )?,
+        ManagementResponse::Unsubscribed { id } => writeln!(out, "✓ Unsubscribed from {}", id)?,
+        ManagementResponse::ActorEvent { id, .. } => {
+            let event = &response.to_display_json()["ActorEvent"]["event"];
+            writeln!(out, "[{}] {}", id, event)?;
+        }
+        ManagementResponse::Unknown { variant, payload } => {
+            writeln!(out, "⚠ Unrecognized {} response: {}", variant, payload)?;
+        }
+        other => writeln!(out, "{}", other.to_display_json())?,
+    }
+    Ok(())
+}
+
+/// Writes a byte payload as indented pretty JSON, text, or its size.
+fn write_payload(out: &mut impl fmt::Write, payload: &[u8]) -> fmt::Result {
+    let text = match serde_json::from_slice::<serde_json::Value>(payload) {
+        Ok(json) => serde_json::to_string_pretty(&json).unwrap_or_default(),
+        Err(_) => match std::str::from_utf8(payload) {
+            Ok(text) => text.to_string(),
+            Err(_) => format!("<{} bytes>", payload.len()),
+        },
+    };
+    for line in text.lines() {
+        writeln!(out, "  {}", line)?;
+    }
+    Ok(())
+}
+
+/// Renders a frontend message to the text `display_message` prints.
+pub fn render_message(msg: &FrontendMessage) -> String {
+    let mut out = String::new();
+    write_message(&mut out, msg).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_message(out: &mut impl fmt::Write, msg: &FrontendMessage) -> fmt::Result {
     match msg {
         FrontendMessage::Status {
             child_running,
             active_operations,
         } => {
-            println!("Status:");
-            println!("  Child running: {}", child_running);
+            writeln!(out, "Status:")?;
+            writeln!(out, "  Child running: {}", child_running)?;
             if !active_operations.is_empty() {
-                println!("  Active operations:");
+                writeln!(out, "  Active operations:")?;
                 for op in active_operations {
-                    println!("    - {} ({})", op.operation_type, op.operation_id);
+                    writeln!(out, "    - {} ({})", op.operation_type, op.operation_id)?;
                 }
             }
         }
@@ -172,18 +744,23 @@ fn display_message(msg: &FrontendMessage, verbose: bool) {
             operation_type,
             description,
         } => {
-            println!("→ {} operation started: {}", operation_type, description);
-            println!("  Operation ID: {}", operation_id);
+            writeln!(
+                out,
+                "→ {} operation started: {}",
+                operation_type, description
+            )?;
+            writeln!(out, "  Operation ID: {}", operation_id)?;
         }
         FrontendMessage::OperationProgress {
             operation_id,
             description,
             percent_complete,
         } => {
-            println!(
+            writeln!(
+                out,
                 "  [{}] {:.1}% - {}",
                 operation_id, percent_complete, description
-            );
+            )?;
         }
         FrontendMessage::OperationCompleted {
             operation_id,
@@ -191,22 +768,26 @@ fn display_message(msg: &FrontendMessage, verbose: bool) {
             message,
         } => {
             let status = if *success { "✓" } else { "✗" };
-            println!(
+            writeln!(
+                out,
                 "{} Operation {} complete: {}",
                 status, operation_id, message
-            );
+            )?;
         }
         FrontendMessage::ChildStarted { child_id } => {
-            println!("✓ Child actor started: {}", child_id);
+            writeln!(out, "✓ Child actor started: {}", child_id)?;
         }
         FrontendMessage::ChildStopped { child_id } => {
-            println!("✓ Child actor stopped: {}", child_id);
+            writeln!(out, "✓ Child actor stopped: {}", child_id)?;
         }
         FrontendMessage::Log { level, message } => {
-            println!("[{}] {}", level, message);
+            writeln!(out, "[{}] {}", level, message)?;
         }
         FrontendMessage::Error { code, message } => {
-            println!("Error {}: {}", code, message);
+            writeln!(out, "Error {}: {}", code, message)?;
+        }
+        FrontendMessage::Unknown { variant, payload } => {
+            writeln!(out, "⚠ Unrecognized {} message: {}", variant, payload)?;
         }
         FrontendMessage::BuildEvent {
             operation_id,
@@ -224,18 +805,19 @@ fn display_message(msg: &FrontendMessage, verbose: bool) {
                     "debug" => "🔍",
                     _ => "·",
                 };
-                println!("  {} [{}] {}", level_marker, level, message);
+                writeln!(out, "  {} [{}] {}", level_marker, level, message)?;
             }
             BuildEventType::Progress => {
                 if let Some(percent) = details.percent_complete {
                     let msg = "In Progress".to_string();
                     let status = details.status.as_ref().unwrap_or(&msg);
-                    println!(
+                    writeln!(
+                        out,
                         "  → [{}] {:>5.1}% - {} ({})",
                         operation_id, percent, message, status
-                    );
+                    )?;
                 } else {
-                    println!("  → [{}] Progress: {}", operation_id, message);
+                    writeln!(out, "  → [{}] Progress: {}", operation_id, message)?;
                 }
             }
             BuildEventType::CommandStarted => {
@@ -243,14 +825,14 @@ fn display_message(msg: &FrontendMessage, verbose: bool) {
                     Some(args) => args.join(" "),
                     None => String::new(),
                 };
-                println!("  $ [{}] Running: {} {}", operation_id, message, args);
+                writeln!(out, "  $ [{}] Running: {} {}", operation_id, message, args)?;
             }
             BuildEventType::CommandOutput => {
                 if let Some(stdout) = &details.stdout {
                     if !stdout.trim().is_empty() {
-                        println!("  │ [{}] Output:", operation_id);
+                        writeln!(out, "  │ [{}] Output:", operation_id)?;
                         for line in stdout.lines() {
-                            println!("  │  {}", line);
+                            writeln!(out, "  │  {}", line)?;
                         }
                     }
                 }
@@ -258,9 +840,9 @@ fn display_message(msg: &FrontendMessage, verbose: bool) {
                     if !stderr.trim().is_empty()
                         && stderr != "Stderr not available from host function"
                     {
-                        println!("  │ [{}] Errors:", operation_id);
+                        writeln!(out, "  │ [{}] Errors:", operation_id)?;
                         for line in stderr.lines() {
-                            println!("  │  {}", line);
+                            writeln!(out, "  │  {}", line)?;
                         }
                     }
                 }
@@ -271,63 +853,245 @@ fn display_message(msg: &FrontendMessage, verbose: bool) {
                 } else {
                     "✗"
                 };
-                println!(
+                writeln!(
+                    out,
                     "  {} [{}] Build complete: {}",
                     status, operation_id, message
-                );
+                )?;
                 if let Some(path) = &details.wasm_path {
-                    println!("  │  WASM file: {}", path);
+                    writeln!(out, "  │  WASM file: {}", path)?;
                 }
                 if let Some(hash) = &details.wasm_hash {
-                    println!("  │  WASM hash: {}", hash);
+                    writeln!(out, "  │  WASM hash: {}", hash)?;
                 }
                 if let Some(error) = &details.error {
-                    println!("  │  Error: {}", error);
+                    writeln!(out, "  │  Error: {}", error)?;
                 }
             }
             BuildEventType::FileExtracted => {
-                println!("  • [{}] Extracted: {}", operation_id, message);
+                writeln!(out, "  • [{}] Extracted: {}", operation_id, message)?;
             }
         },
     }
+    Ok(())
+}
+
+/// Commands about the operations seen so far, answered from the
+/// [`Tracker`] without asking the actor.
+#[derive(Debug, Clone)]
+pub(crate) enum OperationsCommand {
+    List,
+    Show(String),
+    Wait(String),
+}
+
+pub(crate) fn parse_operations_command(line: &str) -> Option<Result<OperationsCommand>> {
+    let line = line.trim();
+    let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let id = || match rest.split_whitespace().next() {
+        Some(id) => Ok(id.to_string()),
+        None => anyhow::bail!("Usage: {} <operation-id>", word),
+    };
+    match word {
+        "ops" => Some(Ok(OperationsCommand::List)),
+        "show" => Some(id().map(OperationsCommand::Show)),
+        "wait" => Some(id().map(OperationsCommand::Wait)),
+        _ => None,
+    }
 }
 
-pub async fn run_repl(actor_id: &str, address: &str, verbose: bool) -> Result<()> {
-    println!(
+pub(crate) async fn run_operations_command(
+    command: OperationsCommand,
+    tracker: &Tracker,
+) -> Result<()> {
+    let (id, full) = match command {
+        OperationsCommand::List => {
+            if output::is_json() {
+                let operations = tracker.with(|ops| ops.iter().map(|op| op.to_json()).collect());
+                output::emit(
+                    "operations",
+                    json!({ "operations": Value::Array(operations) }),
+                );
+            } else {
+                print!("{}", tracker.with(render_operations));
+            }
+            return Ok(());
+        }
+        OperationsCommand::Show(id) => (id, true),
+        OperationsCommand::Wait(id) => {
+            if tracker.with(|ops| ops.get(&id).is_none()) {
+                anyhow::bail!("No operation {}; type 'ops' to list them", id);
+            }
+            tokio::select! {
+                completed = tracker.wait(&id) => if !completed {
+                    say!("Stopped waiting for {}: the session is over", id);
+                    return Ok(());
+                },
+                _ = tokio::signal::ctrl_c() => {
+                    say!("Stopped waiting for {}", id);
+                    return Ok(());
+                }
+                _ = teardown::shutdown_requested() => return Ok(()),
+            }
+            // Its messages were displayed as they came
+            (id, false)
+        }
+    };
+    tracker.with(|ops| {
+        let Some(op) = ops.get(&id) else {
+            anyhow::bail!("No operation {}; type 'ops' to list them", id);
+        };
+        if output::is_json() {
+            output::emit("operation", json!({ "operation": op.to_json() }));
+        } else if full {
+            print!("{}", render_operation(op));
+        } else {
+            print!("{}", render_summary(op));
+        }
+        Ok(())
+    })
+}
+
+/// Completes operation IDs after `show` and `wait`.
+struct ReplHelper {
+    tracker: Tracker,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let prefix = match line.split_once(' ') {
+            Some(("show" | "wait", rest)) => rest.trim_start(),
+            _ => return Ok((pos, Vec::new())),
+        };
+        if prefix.contains(char::is_whitespace) {
+            return Ok((pos, Vec::new()));
+        }
+        let ids = self
+            .tracker
+            .with(|ops| ops.ids_starting_with(prefix).map(String::from).collect());
+        Ok((pos - prefix.len(), ids))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+pub async fn run_repl(
+    actor_id: &str,
+    address: &str,
+    transport: &Transport,
+    verbose: bool,
+    script: Option<Script>,
+) -> Result<()> {
+    say!(
+        "Connecting to {} and opening channel to actor {}",
+        address,
+        actor_id
+    );
+
+    let repl = ChannelRepl::new(address, transport, actor_id).await?;
+    say!("Channel opened successfully");
+
+    run(repl, verbose, script).await
+}
+
+/// Attaches to an already running actor, checking that it answers a status
+/// request before handing the channel to the REPL.
+pub async fn attach(
+    actor_id: &str,
+    address: &str,
+    transport: &Transport,
+    verbose: bool,
+    script: Option<Script>,
+) -> Result<()> {
+    say!(
         "Connecting to {} and opening channel to actor {}",
-        address, actor_id
+        address,
+        actor_id
     );
 
-    let repl = ChannelRepl::new(address, actor_id).await?;
-    println!("Channel opened successfully");
+    let mut repl = ChannelRepl::new(address, transport, actor_id).await?;
+    say!("Channel opened, waiting for the actor to answer...");
 
-    println!("\nType 'help' for available commands\n");
+    let status = repl.ping(Duration::from_secs(10)).await?;
+    display_message(&status);
 
-    let mut rl = rustyline::DefaultEditor::new()?;
+    run(repl, verbose, script).await
+}
+
+async fn run(mut repl: ChannelRepl, verbose: bool, script: Option<Script>) -> Result<()> {
+    if let Some(script) = script {
+        // On shutdown the caller reports the signal and exits with its status
+        let result = tokio::select! {
+            result = script.run(&mut repl, verbose) => result,
+            _ = teardown::shutdown_requested() => Ok(()),
+        };
+        repl.close().await;
+        report_unknown_variants();
+        return result;
+    }
+
+    say!("\nType 'help' for available commands\n");
+    // Ctrl-C ends the REPL, or a `wait`, rather than the whole session
+    let _ctrl_c = teardown::leave_ctrl_c_to_caller();
+
+    let tracker = Tracker::default();
+    // Keep the prompt off stdout when it carries JSON records
+    let behavior = if output::is_json() && std::io::stdin().is_terminal() {
+        Behavior::PreferTerm
+    } else {
+        Behavior::Stdio
+    };
+    let config = Config::builder().behavior(behavior).build();
+    let mut rl = Editor::<ReplHelper, DefaultHistory>::with_config(config)?;
+    rl.set_helper(Some(ReplHelper {
+        tracker: tracker.clone(),
+    }));
+    // The prompt runs on a thread of its own, where a signal cannot cut it
+    // short, so the terminal it leaves in raw mode is put back by hand
+    let terminal = terminal_mode();
+    let mut editor = Some(rl);
 
     // Create channels for coordinating shutdown
     let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
     let _shutdown_tx_clone = shutdown_tx.clone();
 
     // Start message display task
-    let mut message_rx = repl.message_rx;
+    let mut event_rx = repl.event_rx;
     let verbose_setting = verbose;
+    let display_tracker = tracker.clone();
     let display_handle = tokio::spawn(async move {
+        let mut closed = false;
         loop {
             tokio::select! {
-                Some(msg) = message_rx.recv() => {
-                    // Skip BuildEvent messages if not in verbose mode, except for BuildComplete events
-                    let should_display = match &msg {
-                        FrontendMessage::BuildEvent { event_type, .. } => {
-                            verbose_setting || matches!(event_type, BuildEventType::BuildComplete)
-                        },
-                        _ => true,
-                    };
-
-                    if should_display {
-                        display_message(&msg, verbose_setting);
+                event = event_rx.recv(), if !closed => match event {
+                    Some(event) => {
+                        if let ReplEvent::Message(msg) = &event {
+                            display_tracker.record(msg);
+                        }
+                        display_event(&event, verbose_setting)
                     }
-                }
+                    // The channel closed or reconnecting gave up
+                    None => {
+                        display_tracker.close();
+                        closed = true;
+                    }
+                },
                 _ = shutdown_rx.recv() => {
                     break;
                 }
@@ -340,27 +1104,61 @@ pub async fn run_repl(actor_id: &str, address: &str, verbose: bool) -> Result<()
 
     // Handle user input
     loop {
-        let readline = rl.readline("repl> ");
+        let mut rl = editor
+            .take()
+            .expect("the editor is back after every prompt");
+        let (line_tx, line_rx) = oneshot::channel();
+        std::thread::spawn(move || {
+            let line = rl.readline("repl> ");
+            let _ = line_tx.send((rl, line));
+        });
+        let readline = tokio::select! {
+            read = line_rx => {
+                let (rl, line) = read.expect("the prompt thread always answers");
+                editor = Some(rl);
+                line
+            }
+            _ = teardown::shutdown_requested() => {
+                restore_terminal_mode(&terminal);
+                say!();
+                let disconnect = ReplCommand::Frontend(FrontendCommand::Disconnect);
+                let _ = command_tx.send(disconnect).await;
+                break;
+            }
+        };
         match readline {
             Ok(line) => {
-                let _ = rl.add_history_entry(line.as_str());
+                if let Some(rl) = editor.as_mut() {
+                    let _ = rl.add_history_entry(line.as_str());
+                }
+
+                if let Some(command) = parse_operations_command(&line) {
+                    let result = match command {
+                        Ok(command) => run_operations_command(command, &tracker).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = result {
+                        say!("Error: {}", e);
+                    }
+                    continue;
+                }
 
                 match parse_command(&line) {
                     Ok(cmd) => {
-                        if matches!(cmd, FrontendCommand::Disconnect) {
+                        if cmd.is_disconnect() {
                             if let Err(e) = command_tx.send(cmd).await {
-                                println!("Error sending disconnect: {}", e);
+                                say!("Error sending disconnect: {}", e);
                             }
                             break;
                         }
                         if let Err(e) = command_tx.send(cmd).await {
-                            println!("Error sending command: {}", e);
+                            say!("Error sending command: {}", e);
                             break;
                         }
                     }
                     Err(e) => {
                         if !e.to_string().is_empty() {
-                            println!("Error: {}", e);
+                            say!("Error: {}", e);
                         }
                     }
                 }
@@ -368,13 +1166,14 @@ pub async fn run_repl(actor_id: &str, address: &str, verbose: bool) -> Result<()
             Err(rustyline::error::ReadlineError::Interrupted)
             | Err(rustyline::error::ReadlineError::Eof) => {
                 // Send disconnect on Ctrl-C or Ctrl-D
-                if let Err(e) = command_tx.send(FrontendCommand::Disconnect).await {
-                    println!("Error sending disconnect: {}", e);
+                let disconnect = ReplCommand::Frontend(FrontendCommand::Disconnect);
+                if let Err(e) = command_tx.send(disconnect).await {
+                    say!("Error sending disconnect: {}", e);
                 }
                 break;
             }
             Err(err) => {
-                println!("Error: {}", err);
+                say!("Error: {}", err);
                 break;
             }
         }
@@ -385,10 +1184,45 @@ pub async fn run_repl(actor_id: &str, address: &str, verbose: bool) -> Result<()
 
     // Wait for display task to finish
     if let Err(e) = display_handle.await {
-        println!("Error in message display task: {}", e);
+        say!("Error in message display task: {}", e);
     }
 
-    println!("Goodbye!");
+    report_unknown_variants();
+    say!("Goodbye!");
     Ok(())
 }
 
+#[cfg(unix)]
+type TerminalMode = Option<nix::sys::termios::Termios>;
+#[cfg(not(unix))]
+type TerminalMode = ();
+
+/// The settings of the terminal on stdin, if it is one.
+fn terminal_mode() -> TerminalMode {
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsRawFd;
+        nix::sys::termios::tcgetattr(std::io::stdin().as_raw_fd()).ok()
+    }
+}
+
+fn restore_terminal_mode(mode: &TerminalMode) {
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use nix::sys::termios::{tcsetattr, SetArg};
+        use std::os::fd::AsRawFd;
+        let _ = tcsetattr(std::io::stdin().as_raw_fd(), SetArg::TCSANOW, mode);
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+}
+
+fn report_unknown_variants() {
+    let unknown = unknown_variants_seen();
+    if unknown > 0 {
+        say!(
+            "⚠ {} message(s) from the server were not recognized; a newer manager-interface may understand them",
+            unknown
+        );
+    }
+}
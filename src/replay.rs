@@ -0,0 +1,120 @@
+use crate::protocol::FrontendMessage;
+use crate::recording::{Direction, Entry};
+use crate::repl::{display_event, ReplEvent};
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset};
+use std::path::Path;
+use std::time::Duration;
+
+/// A frontend message from a recording, with when it was received.
+#[derive(Debug)]
+pub struct Recorded {
+    pub at: DateTime<FixedOffset>,
+    pub message: FrontendMessage,
+}
+
+/// Which recorded messages to show: those about `operation`, if given, and
+/// of one of `types`, if any are given. Types match variant names such as
+/// `BuildEvent`, ignoring case.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub operation: Option<String>,
+    pub types: Vec<String>,
+}
+
+impl Filter {
+    pub fn matches(&self, message: &FrontendMessage) -> bool {
+        let operation = match &self.operation {
+            Some(id) => message.operation_id() == Some(id.as_str()),
+            None => true,
+        };
+        let kind = self.types.is_empty()
+            || self
+                .types
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(message.name()));
+        operation && kind
+    }
+}
+
+/// How fast to replay: at the recorded pace divided by a speed-up factor,
+/// or all at once.
+#[derive(Debug, Clone, Copy)]
+pub enum Pace {
+    Recorded { speed: f64 },
+    Instant,
+}
+
+/// Reads a recording written by `--record`.
+pub fn load(path: &Path) -> Result<Vec<Entry>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read recording {}", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let entry: Entry = serde_json::from_str(line)
+                .with_context(|| format!("{}:{}: invalid entry", path.display(), index + 1))?;
+            if entry.direction != Direction::Connected && entry.frame.is_none() {
+                anyhow::bail!("{}:{}: entry has no frame", path.display(), index + 1);
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// The frontend messages received on channels, in recorded order, and how
+/// many channel messages were skipped because they are not frontend
+/// messages.
+pub fn messages(entries: &[Entry]) -> Result<(Vec<Recorded>, usize)> {
+    let mut messages = Vec::new();
+    let mut skipped = 0;
+    for entry in entries {
+        if entry.direction != Direction::Received {
+            continue;
+        }
+        let Some(message) = channel_message(entry) else {
+            continue;
+        };
+        let Ok(message) = message else {
+            skipped += 1;
+            continue;
+        };
+        let at = DateTime::parse_from_rfc3339(&entry.timestamp)
+            .with_context(|| format!("Invalid timestamp {}", entry.timestamp))?;
+        messages.push(Recorded { at, message });
+    }
+    Ok((messages, skipped))
+}
+
+/// The message of a recorded `ChannelMessage`, read from the frame as sent
+/// on the wire.
+fn channel_message(entry: &Entry) -> Option<serde_json::Result<FrontendMessage>> {
+    let message = entry
+        .frame
+        .as_ref()?
+        .get("ChannelMessage")?
+        .get("message")?;
+    let bytes: Vec<u8> = serde_json::from_value(message.clone()).ok()?;
+    Some(serde_json::from_slice(&bytes))
+}
+
+/// Displays the messages that pass `filter` the way the REPL showed them,
+/// waiting between them according to `pace`. Returns how many were shown.
+pub async fn play(messages: Vec<Recorded>, filter: &Filter, pace: Pace, verbose: bool) -> usize {
+    let mut previous: Option<DateTime<FixedOffset>> = None;
+    let mut shown = 0;
+    for recorded in messages {
+        if !filter.matches(&recorded.message) {
+            continue;
+        }
+        if let (Pace::Recorded { speed }, Some(previous)) = (pace, previous) {
+            let gap = (recorded.at - previous).to_std().unwrap_or_default();
+            tokio::time::sleep(Duration::from_secs_f64(gap.as_secs_f64() / speed)).await;
+        }
+        previous = Some(recorded.at);
+        display_event(&ReplEvent::Message(recorded.message), verbose);
+        shown += 1;
+    }
+    shown
+}
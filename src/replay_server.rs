@@ -0,0 +1,235 @@
+use crate::protocol::{ManagementCommand, ManagementResponse, PROTOCOL_VERSION};
+use crate::recording::{Direction, Entry};
+use crate::replay;
+use crate::transport::{Listener, Stream};
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use futures::sink::SinkExt;
+use futures::stream::StreamExt;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+/// A command the recorded client sent and everything it received before
+/// sending the next one.
+#[derive(Debug)]
+struct Exchange {
+    command: Value,
+    replies: Vec<ManagementResponse>,
+}
+
+/// The exchanges of a recording, one list per connection in the order the
+/// connections were opened.
+#[derive(Debug)]
+pub struct Transcript {
+    connections: VecDeque<Vec<Exchange>>,
+}
+
+impl Transcript {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_entries(&replay::load(path)?)
+    }
+
+    pub fn from_entries(entries: &[Entry]) -> Result<Self> {
+        let mut connections = Vec::new();
+        // Connection IDs start over in every recorded run, so each
+        // `connected` entry starts a new connection
+        let mut index: HashMap<u64, usize> = HashMap::new();
+        for entry in entries {
+            match entry.direction {
+                Direction::Connected => {
+                    index.insert(entry.connection, connections.len());
+                    connections.push(Vec::new());
+                }
+                Direction::Sent => {
+                    let (Some(&i), Some(command)) = (index.get(&entry.connection), &entry.command)
+                    else {
+                        continue;
+                    };
+                    connections[i].push(Exchange {
+                        command: command.clone(),
+                        replies: Vec::new(),
+                    });
+                }
+                Direction::Received => {
+                    let Some(&i) = index.get(&entry.connection) else {
+                        continue;
+                    };
+                    let Some(exchange) = connections[i].last_mut() else {
+                        continue;
+                    };
+                    // The frame as it was received replays byte for byte
+                    let frame = entry
+                        .frame
+                        .as_ref()
+                        .ok_or_else(|| anyhow::anyhow!("Received entry has no frame"))?;
+                    let response = serde_json::from_value(frame.clone())
+                        .with_context(|| format!("Could not rebuild response {}", frame))?;
+                    exchange.replies.push(response);
+                }
+            }
+        }
+        Ok(Self {
+            connections: connections.into(),
+        })
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.len()
+    }
+}
+
+/// Plays a transcript back to clients: each connection accepted takes the
+/// next recorded connection, and each command that matches the next one
+/// recorded on it is answered with the recorded responses and channel
+/// messages. Anything else is answered with an `Error` naming what the
+/// recording expected.
+pub async fn serve(listener: Listener, transcript: Transcript, log: bool) -> Result<()> {
+    let connections = Arc::new(Mutex::new(transcript.connections));
+    let mut accepted = 0u64;
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        accepted += 1;
+        let connection = accepted;
+        let exchanges = connections.lock().unwrap().pop_front().unwrap_or_default();
+        if log {
+            println!(
+                "[conn {}] connected from {}, replaying {} exchange(s)",
+                connection,
+                peer,
+                exchanges.len()
+            );
+        }
+
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, exchanges, connection, log).await {
+                if log {
+                    println!("[conn {}] error: {}", connection, e);
+                }
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: Box<dyn Stream>,
+    exchanges: Vec<Exchange>,
+    connection: u64,
+    log: bool,
+) -> Result<()> {
+    let mut codec = LengthDelimitedCodec::new();
+    codec.set_max_frame_length(32 * 1024 * 1024);
+    let mut framed = Framed::new(stream, codec);
+    let mut exchanges = exchanges.into_iter().peekable();
+
+    while let Some(frame) = framed.next().await {
+        let frame = frame?;
+        let replies = match serde_json::from_slice::<ManagementCommand>(&frame) {
+            Ok(command) => {
+                let actual = command.to_display_json();
+                // `actual` is only compared against the recording; the live
+                // command may hold secrets the recording has masked
+                if log {
+                    println!("[conn {}] <- {}", connection, command.name());
+                }
+                // Clients only greet a server they have not met before, so
+                // the replaying client may greet where the recorded one did
+                // not, or the other way round
+                let greeting = matches!(command, ManagementCommand::Handshake { .. });
+                let expected = exchanges
+                    .peek()
+                    .is_some_and(|next| is_handshake(&next.command));
+                if expected && !greeting {
+                    exchanges.next();
+                }
+                match exchanges.peek() {
+                    _ if greeting && !expected => vec![server_info()],
+                    Some(next) if matches(&next.command, &actual) => {
+                        exchanges.next().map(|e| e.replies).unwrap_or_default()
+                    }
+                    Some(next) => vec![ManagementResponse::Error {
+                        message: format!("Replay expected {}, got {}", next.command, actual),
+                    }],
+                    None => vec![ManagementResponse::Error {
+                        message: format!(
+                            "Replay has nothing more on this connection, got {}",
+                            actual
+                        ),
+                    }],
+                }
+            }
+            Err(e) => vec![ManagementResponse::Error {
+                message: format!("Invalid command: {}", e),
+            }],
+        };
+        for reply in replies {
+            framed
+                .send(Bytes::from(serde_json::to_vec(&reply)?))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+fn is_handshake(command: &Value) -> bool {
+    command.get("Handshake").is_some()
+}
+
+/// What a server that speaks this client's protocol answers a handshake with.
+fn server_info() -> ManagementResponse {
+    ManagementResponse::ServerInfo {
+        protocol_version: PROTOCOL_VERSION,
+        min_protocol_version: PROTOCOL_VERSION,
+        commands: ManagementCommand::NAMES
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+    }
+}
+
+/// Whether a command matches the recorded one. Redacted strings match any
+/// string, handshakes match whatever client version sent them, and a
+/// manifest recorded relative to the profile matches wherever the profile is
+/// now.
+fn matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) if expected.contains_key("Handshake") => {
+            actual.contains_key("Handshake")
+        }
+        (Value::Object(expected), Value::Object(actual)) if expected.contains_key("manifest") => {
+            fn manifest(fields: &serde_json::Map<String, Value>) -> Option<&Path> {
+                fields
+                    .get("manifest")
+                    .and_then(Value::as_str)
+                    .map(Path::new)
+            }
+            let same_manifest = match (manifest(expected), manifest(actual)) {
+                (Some(expected), Some(actual)) if expected.is_relative() => {
+                    actual.ends_with(expected)
+                }
+                (expected, actual) => expected == actual,
+            };
+            same_manifest
+                && expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .filter(|(key, _)| *key != "manifest")
+                    .all(|(key, value)| actual.get(key).is_some_and(|a| matches(value, a)))
+        }
+        (Value::String(expected), Value::String(_)) if expected.contains("***") => true,
+        (Value::Object(expected), Value::Object(actual)) => {
+            expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .all(|(key, value)| actual.get(key).is_some_and(|a| matches(value, a)))
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            expected.len() == actual.len()
+                && expected.iter().zip(actual).all(|(e, a)| matches(e, a))
+        }
+        (expected, actual) => expected == actual,
+    }
+}
@@ -0,0 +1,272 @@
+use crate::oneshot::{active_operations, execute, next_message, succeeded};
+use crate::operations::Tracker;
+use crate::protocol::*;
+use crate::repl::{
+    display_event, parse_command, parse_operations_command, run_operations_command, ChannelRepl,
+    OperationsCommand, ReplCommand, ReplEvent,
+};
+use crate::say;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+/// REPL commands read from a file or stdin, run one after the other.
+#[derive(Debug)]
+pub struct Script {
+    source: String,
+    lines: Vec<Line>,
+}
+
+#[derive(Debug)]
+struct Line {
+    number: usize,
+    text: String,
+    step: Step,
+}
+
+#[derive(Debug)]
+enum Step {
+    /// Runs a command, which must succeed, as must an operation it starts.
+    Command(ReplCommand),
+    /// Wait until every operation active on the manager has completed; fails
+    /// if any of them did not succeed.
+    Wait,
+    /// `ops`, `show <op-id>` or `wait <op-id>`, as in the REPL. Waiting for
+    /// an operation fails if it does not succeed.
+    Operations(OperationsCommand),
+    Sleep(Duration),
+    Echo(String),
+    /// Stop here, like `exit` in the REPL.
+    Exit,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read script {}", path.display()))?;
+        Self::parse(&path.display().to_string(), &text)
+    }
+
+    pub fn from_stdin() -> Result<Self> {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .context("Could not read script from stdin")?;
+        Self::parse("<stdin>", &text)
+    }
+
+    /// Parses a script, one command or directive per line. Blank lines and
+    /// lines starting with `#` are skipped.
+    pub fn parse(source: &str, text: &str) -> Result<Self> {
+        let mut lines = Vec::new();
+        for (index, text) in text.lines().enumerate() {
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let step =
+                parse_step(text).with_context(|| format!("{}:{}: {}", source, index + 1, text))?;
+            lines.push(Line {
+                number: index + 1,
+                text: text.to_string(),
+                step,
+            });
+        }
+        Ok(Self {
+            source: source.to_string(),
+            lines,
+        })
+    }
+
+    /// Runs every line in order, each command blocking until its operation
+    /// has finished. Stops at the first line that fails.
+    pub async fn run(&self, repl: &mut ChannelRepl, verbose: bool) -> Result<()> {
+        let tracker = Tracker::default();
+        repl.track(tracker.clone());
+        for line in &self.lines {
+            let result = match &line.step {
+                Step::Exit => break,
+                step => run_step(repl, step, &line.text, &tracker, verbose).await,
+            };
+            result.with_context(|| format!("{}:{}: {}", self.source, line.number, line.text))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_step(text: &str) -> Result<Step> {
+    let (word, rest) = text.split_once(' ').unwrap_or((text, ""));
+    let rest = rest.trim();
+    match word {
+        "wait" if rest.is_empty() => Ok(Step::Wait),
+        "sleep" => Ok(Step::Sleep(parse_duration(rest)?)),
+        "echo" => Ok(Step::Echo(rest.to_string())),
+        "help" => anyhow::bail!("help is only available in the interactive REPL"),
+        _ => {
+            if let Some(command) = parse_operations_command(text) {
+                return Ok(Step::Operations(command?));
+            }
+            match parse_command(text)? {
+                ReplCommand::Frontend(FrontendCommand::Disconnect) => Ok(Step::Exit),
+                command => Ok(Step::Command(command)),
+            }
+        }
+    }
+}
+
+/// Parses `500ms`, `5s`, `2m` or a bare number of seconds.
+fn parse_duration(text: &str) -> Result<Duration> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Usage: sleep <duration>, e.g. 5s or 500ms"))?;
+    let seconds = match unit {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        _ => anyhow::bail!("Unknown unit `{}`; use ms, s or m", unit),
+    };
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| anyhow::anyhow!("Duration `{}` is out of range", text))
+}
+
+async fn run_step(
+    repl: &mut ChannelRepl,
+    step: &Step,
+    text: &str,
+    tracker: &Tracker,
+    verbose: bool,
+) -> Result<()> {
+    match step {
+        Step::Command(ReplCommand::Frontend(command)) => {
+            let outcome = execute(repl, command.clone(), verbose).await?;
+            if !succeeded(&outcome) {
+                let message = match &outcome {
+                    FrontendMessage::OperationCompleted { message, .. } => message.as_str(),
+                    _ => "",
+                };
+                anyhow::bail!("`{}` failed: {}", text, message);
+            }
+        }
+        Step::Command(ReplCommand::Management(command)) => {
+            manage(repl, command.clone(), text, verbose).await?
+        }
+        Step::Wait => wait_for_all(repl, verbose).await?,
+        Step::Operations(command) => operations(repl, command, tracker, verbose).await?,
+        Step::Sleep(duration) => tokio::time::sleep(*duration).await,
+        Step::Echo(text) => say!("{}", text),
+        Step::Exit => {}
+    }
+    Ok(())
+}
+
+/// Sends a management command and waits for the server to answer it,
+/// failing if the server rejects it.
+async fn manage(
+    repl: &mut ChannelRepl,
+    command: ManagementCommand,
+    text: &str,
+    verbose: bool,
+) -> Result<()> {
+    repl.send_management(command).await?;
+    loop {
+        match repl.recv().await {
+            Some(ReplEvent::Management(
+                response @ (ManagementResponse::ActorEvent { .. }
+                | ManagementResponse::Unknown { .. }),
+            )) => display_event(&ReplEvent::Management(response), verbose),
+            Some(event @ ReplEvent::Management(_)) => {
+                display_event(&event, verbose);
+                return Ok(());
+            }
+            Some(ReplEvent::Failed { command, error }) => {
+                let message = format!("`{}` failed: {}", text, error);
+                display_event(&ReplEvent::Failed { command, error }, verbose);
+                anyhow::bail!(message)
+            }
+            Some(ReplEvent::GaveUp { attempts, error }) => {
+                anyhow::bail!("Connection lost after {} attempt(s): {}", attempts, error)
+            }
+            Some(event) => display_event(&event, verbose),
+            None => anyhow::bail!("Channel closed before the command finished"),
+        }
+    }
+}
+
+/// Runs an operations command with the REPL's handler. Nothing else reads
+/// the channel meanwhile, so what arrives is displayed, and so tracked, here
+/// until the handler is done.
+async fn operations(
+    repl: &mut ChannelRepl,
+    command: &OperationsCommand,
+    tracker: &Tracker,
+    verbose: bool,
+) -> Result<()> {
+    let waited_for = match command {
+        OperationsCommand::Wait(id) => Some(id.clone()),
+        _ => None,
+    };
+    let handled = async {
+        tokio::select! {
+            result = run_operations_command(command.clone(), tracker) => result,
+            error = display_until_closed(repl, verbose) => Err(error),
+        }
+    };
+    handled.await?;
+
+    if let Some(id) = waited_for {
+        let outcome = tracker.with(|ops| ops.get(&id).and_then(|op| op.outcome.clone()));
+        match outcome {
+            Some((true, _)) => {}
+            Some((false, message)) => anyhow::bail!("operation {} failed: {}", id, message),
+            None => anyhow::bail!("operation {} did not complete", id),
+        }
+    }
+    Ok(())
+}
+
+/// Displays events until the channel is gone, returning why.
+async fn display_until_closed(repl: &mut ChannelRepl, verbose: bool) -> anyhow::Error {
+    loop {
+        match repl.recv().await {
+            Some(ReplEvent::GaveUp { attempts, error }) => {
+                return anyhow::anyhow!("Connection lost after {} attempt(s): {}", attempts, error)
+            }
+            Some(event) => display_event(&event, verbose),
+            None => return anyhow::anyhow!("Channel closed before the operation completed"),
+        }
+    }
+}
+
+/// Asks the manager which operations are active and waits for all of them
+/// to complete, failing if any of them did not succeed.
+async fn wait_for_all(repl: &mut ChannelRepl, verbose: bool) -> Result<()> {
+    let mut active: HashSet<String> = active_operations(repl, verbose)
+        .await?
+        .into_iter()
+        .collect();
+    let mut failed = Vec::new();
+    while !active.is_empty() {
+        let msg = next_message(repl, verbose).await?;
+        if let FrontendMessage::OperationCompleted {
+            operation_id,
+            success,
+            ..
+        } = &msg
+        {
+            if active.remove(operation_id) && !success {
+                failed.push(operation_id.clone());
+            }
+        }
+        display_event(&ReplEvent::Message(msg), verbose);
+    }
+    if !failed.is_empty() {
+        anyhow::bail!("operation(s) {} failed", failed.join(", "));
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Where the value of a `${secrets.<name>}` placeholder comes from.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "source", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum SecretSource {
+    /// An environment variable.
+    Env { var: String },
+    /// The contents of a file that only its owner may read. A leading `~/`
+    /// stands for the home directory.
+    File { path: PathBuf },
+    /// The standard output of a command, e.g. a password manager.
+    Command { command: Vec<String> },
+}
+
+impl SecretSource {
+    pub async fn resolve(&self) -> Result<String> {
+        match self {
+            SecretSource::Env { var } => std::env::var(var)
+                .map_err(|_| anyhow::anyhow!("{} environment variable not set", var)),
+            SecretSource::File { path } => {
+                let path = &expand_home(path)?;
+                check_permissions(path)?;
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Could not read secret file {}", path.display()))?;
+                Ok(contents.trim_end_matches(['\r', '\n']).to_string())
+            }
+            SecretSource::Command { command } => {
+                let (program, args) = command
+                    .split_first()
+                    .ok_or_else(|| anyhow::anyhow!("Secret command is empty"))?;
+                let output = tokio::process::Command::new(program)
+                    .args(args)
+                    .stderr(std::process::Stdio::inherit())
+                    .output()
+                    .await
+                    .with_context(|| format!("Could not run secret command `{}`", program))?;
+                if !output.status.success() {
+                    anyhow::bail!("Secret command `{}` failed: {}", program, output.status);
+                }
+                let stdout = String::from_utf8(output.stdout).with_context(|| {
+                    format!("Secret command `{}` printed invalid UTF-8", program)
+                })?;
+                Ok(stdout.trim_end_matches(['\r', '\n']).to_string())
+            }
+        }
+    }
+}
+
+/// Parses the `--secret NAME=SOURCE` form of a source: `env:VAR`,
+/// `file:PATH` or `command:PROGRAM ARGS...`.
+impl FromStr for SecretSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("env", var)) => Ok(SecretSource::Env {
+                var: var.to_string(),
+            }),
+            Some(("file", path)) => Ok(SecretSource::File { path: path.into() }),
+            Some(("command", command)) => Ok(SecretSource::Command {
+                command: command.split_whitespace().map(str::to_string).collect(),
+            }),
+            _ => Err(format!(
+                "expected env:VAR, file:PATH or command:PROGRAM ARGS, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
+fn expand_home(path: &Path) -> Result<PathBuf> {
+    match path.strip_prefix("~") {
+        Ok(rest) => {
+            let home = std::env::var_os("HOME").ok_or_else(|| {
+                anyhow::anyhow!("Cannot expand {}: HOME is not set", path.display())
+            })?;
+            Ok(PathBuf::from(home).join(rest))
+        }
+        Err(_) => Ok(path.to_path_buf()),
+    }
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(path)
+        .with_context(|| format!("Could not read secret file {}", path.display()))?
+        .permissions()
+        .mode();
+    if mode & 0o077 != 0 {
+        anyhow::bail!(
+            "Secret file {} is accessible by other users (mode {:o}); run `chmod 600 {}`",
+            path.display(),
+            mode & 0o777,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Shortest value [`Redactor::add`] accepts. Masking is by plain substring,
+/// so anything shorter would mangle unrelated output.
+const MIN_SECRET_LEN: usize = 6;
+
+/// What a secret is shown as once masked.
+pub const MASK: &str = "***";
+
+/// Replaces resolved secret values with [`MASK`] in anything that is printed or
+/// written out. Clones share the same set of secrets.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    secrets: Arc<RwLock<Vec<String>>>,
+}
+
+impl Redactor {
+    /// The redactor shared by every client in the process, so a secret
+    /// registered on one connection is masked on all of them.
+    pub fn global() -> Self {
+        static GLOBAL: OnceLock<Redactor> = OnceLock::new();
+        GLOBAL.get_or_init(Redactor::default).clone()
+    }
+
+    /// Registers a secret to mask. Values shorter than six characters are
+    /// ignored: they are not credentials worth hiding and would match
+    /// ordinary text.
+    pub fn add(&self, secret: &str) {
+        if secret.len() >= MIN_SECRET_LEN {
+            self.secrets.write().unwrap().push(secret.to_string());
+        }
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        self.secrets
+            .read()
+            .unwrap()
+            .iter()
+            .fold(text.to_string(), |text, secret| text.replace(secret, MASK))
+    }
+
+    pub fn redact_value(&self, value: &Value) -> Value {
+        match value {
+            Value::String(s) => Value::String(self.redact(s)),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| self.redact_value(v)).collect())
+            }
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), self.redact_value(value)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    /// Masks secrets in raw bytes, which may or may not be text.
+    pub fn redact_bytes(&self, bytes: &[u8]) -> Vec<u8> {
+        let secrets = self.secrets.read().unwrap();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut rest = bytes;
+        'outer: while !rest.is_empty() {
+            for secret in secrets.iter().map(String::as_bytes) {
+                if rest.starts_with(secret) {
+                    out.extend_from_slice(MASK.as_bytes());
+                    rest = &rest[secret.len()..];
+                    continue 'outer;
+                }
+            }
+            out.push(rest[0]);
+            rest = &rest[1..];
+        }
+        out
+    }
+
+    /// Like [`Redactor::redact_value`], for a frame as it is sent on the
+    /// wire: byte payloads, which are arrays of numbers there, are masked
+    /// too, at any depth.
+    pub fn redact_frame(&self, value: &Value) -> Value {
+        match value {
+            Value::Array(items) if !items.is_empty() => match as_bytes(items) {
+                Some(bytes) => Value::from(self.redact_bytes(&bytes)),
+                None => Value::Array(items.iter().map(|v| self.redact_frame(v)).collect()),
+            },
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), self.redact_frame(value)))
+                    .collect(),
+            ),
+            other => self.redact_value(other),
+        }
+    }
+}
+
+fn as_bytes(items: &[Value]) -> Option<Vec<u8>> {
+    items
+        .iter()
+        .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+        .collect()
+}
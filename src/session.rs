@@ -0,0 +1,51 @@
+use crate::bootstrap::Bootstrapped;
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// IDs from the last successful bootstrap, written so a later run can
+/// `--resume` instead of creating fresh stores and actors.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    /// Actor the REPL attaches to.
+    pub repl: String,
+    pub stores: IndexMap<String, String>,
+    pub actors: IndexMap<String, String>,
+}
+
+impl Session {
+    pub fn new(repl: &str, system: &Bootstrapped) -> Self {
+        Self {
+            repl: repl.to_string(),
+            stores: system.stores.clone(),
+            actors: system.actors.clone(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read session file {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid session file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Could not write session file {}", path.display()))
+    }
+
+    /// Drops `(name, id)` actors that were stopped, so a later `--resume`
+    /// starts fresh ones on the same stores instead of probing stale IDs.
+    pub fn forget_actors(&mut self, stopped: &[(String, String)]) {
+        self.actors
+            .retain(|name, id| !stopped.iter().any(|(n, i)| n == name && i == id));
+    }
+
+    pub fn repl_actor_id(&self) -> Result<&str> {
+        self.actors
+            .get(&self.repl)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow::anyhow!("Session has no ID for REPL actor `{}`", self.repl))
+    }
+}
@@ -0,0 +1,198 @@
+use crate::client::ManagementClient;
+use crate::output;
+use crate::say;
+use anyhow::Result;
+use serde_json::json;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::watch;
+
+/// Actors started by this process, in start order. Clones share the same
+/// list, and stopping them takes every actor recorded so far, so none is
+/// stopped twice.
+#[derive(Debug, Clone, Default)]
+pub struct StartedActors {
+    actors: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+impl StartedActors {
+    pub fn push(&self, name: &str, id: &str) {
+        self.actors
+            .lock()
+            .unwrap()
+            .push((name.to_string(), id.to_string()));
+    }
+
+    fn take(&self) -> Vec<(String, String)> {
+        std::mem::take(&mut *self.actors.lock().unwrap())
+    }
+
+    /// Stops every tracked actor in reverse start order, reporting each one.
+    pub async fn stop_all(&self, client: &mut ManagementClient) -> TeardownReport {
+        let mut report = TeardownReport::default();
+        let actors = self.take();
+        if actors.is_empty() {
+            return report;
+        }
+
+        say!("Stopping actors started by this session...");
+        for (name, id) in actors.into_iter().rev() {
+            match client.stop_actor(&id).await {
+                Ok(()) => {
+                    say!("  Stopped {} ({})", name, id);
+                    output::emit("actor_stopped", json!({ "name": name, "id": id }));
+                    report.stopped.push((name, id));
+                }
+                Err(e) => {
+                    say!("  Could not stop {} ({}): {}", name, id, e);
+                    output::emit(
+                        "actor_stop_failed",
+                        json!({ "name": name, "id": id, "error": e.to_string() }),
+                    );
+                    report.failed.push((name, id, e.to_string()));
+                }
+            }
+        }
+        report
+    }
+}
+
+/// Outcome of stopping the tracked actors: `(name, id)` of each stopped
+/// actor and `(name, id, error)` of each one that could not be stopped.
+#[derive(Debug, Default)]
+pub struct TeardownReport {
+    pub stopped: Vec<(String, String)>,
+    pub failed: Vec<(String, String, String)>,
+}
+
+/// Set while the REPL handles Ctrl-C itself; see [`leave_ctrl_c_to_caller`].
+static CTRL_C_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`shutdown_signal`] ignore Ctrl-C until the returned guard is
+/// dropped, for code that gives Ctrl-C a meaning of its own. SIGTERM still
+/// shuts down.
+pub fn leave_ctrl_c_to_caller() -> CtrlCTaken {
+    CTRL_C_TAKEN.store(true, Ordering::SeqCst);
+    CtrlCTaken(())
+}
+
+/// Hands Ctrl-C back to [`shutdown_signal`] when dropped.
+pub struct CtrlCTaken(());
+
+impl Drop for CtrlCTaken {
+    fn drop(&mut self) {
+        CTRL_C_TAKEN.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A signal that ends the session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Ctrl-C, or SIGINT.
+    Interrupt,
+    /// SIGTERM.
+    Terminate,
+}
+
+impl Signal {
+    /// The exit status of a process ended by this signal, as shells report it.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Signal::Interrupt => 130,
+            Signal::Terminate => 143,
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signal::Interrupt => write!(f, "Interrupted"),
+            Signal::Terminate => write!(f, "Terminated"),
+        }
+    }
+}
+
+/// Resolves on Ctrl-C, unless it is left to someone else, or on SIGTERM on
+/// Unix, with the signal received.
+pub async fn shutdown_signal() -> Signal {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = ctrl_c() => Signal::Interrupt,
+                    _ = sigterm.recv() => Signal::Terminate,
+                }
+            }
+            Err(_) => {
+                ctrl_c().await;
+                Signal::Interrupt
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        ctrl_c().await;
+        Signal::Interrupt
+    }
+}
+
+/// The signal the session is shutting down for, once one was received.
+fn shutdown() -> &'static watch::Sender<Option<Signal>> {
+    static SHUTDOWN: OnceLock<watch::Sender<Option<Signal>>> = OnceLock::new();
+    SHUTDOWN.get_or_init(|| watch::channel(None).0)
+}
+
+/// Asks whatever is running to wind down so the main task can stop the
+/// actors it started and exit. Only the first signal counts.
+pub fn begin_shutdown(signal: Signal) {
+    shutdown().send_if_modified(|current| {
+        if current.is_some() {
+            return false;
+        }
+        *current = Some(signal);
+        true
+    });
+}
+
+/// The signal the session is shutting down for, if any.
+pub fn shutting_down() -> Option<Signal> {
+    *shutdown().borrow()
+}
+
+/// Fails once shutdown has begun, so nothing new is started.
+pub fn check_shutdown() -> Result<()> {
+    match shutting_down() {
+        Some(signal) => anyhow::bail!("{}", signal),
+        None => Ok(()),
+    }
+}
+
+/// Resolves once shutdown has begun, with the signal that began it.
+pub async fn shutdown_requested() -> Signal {
+    let mut receiver = shutdown().subscribe();
+    loop {
+        if let Some(signal) = *receiver.borrow_and_update() {
+            return signal;
+        }
+        // The sender lives in a static and is never dropped
+        let _ = receiver.changed().await;
+    }
+}
+
+/// Resolves on the first Ctrl-C that is not left to someone else.
+async fn ctrl_c() {
+    loop {
+        if tokio::signal::ctrl_c().await.is_err() {
+            // No way to listen for it; only SIGTERM can shut down
+            std::future::pending::<()>().await;
+        }
+        if !CTRL_C_TAKEN.load(Ordering::SeqCst) {
+            return;
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+/// Prefix that marks an address as the path of a Unix domain socket.
+pub const UNIX_PREFIX: &str = "unix:";
+
+/// A byte stream to the theater server, whatever carries it.
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// A connection to the theater server, split into management frames.
+pub type FramedStream = Framed<Box<dyn Stream>, LengthDelimitedCodec>;
+
+/// Settings for connecting over TLS.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// PEM bundle of CA certificates to trust instead of the built-in roots.
+    pub ca: Option<PathBuf>,
+    /// PEM client certificate chain and key, for servers that ask for one.
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    /// Name the server certificate must be valid for; defaults to the host
+    /// part of the address.
+    pub server_name: Option<String>,
+}
+
+/// How connections to the theater server are made. The bootstrap and the
+/// REPL share one, so both use the same security settings.
+#[derive(Clone, Default)]
+pub enum Transport {
+    #[default]
+    Tcp,
+    Tls {
+        connector: TlsConnector,
+        server_name: Option<ServerName<'static>>,
+    },
+}
+
+impl Transport {
+    pub fn tls(options: &TlsOptions) -> Result<Self> {
+        let mut roots = RootCertStore::empty();
+        match &options.ca {
+            Some(path) => {
+                for cert in load_certs(path)? {
+                    roots
+                        .add(cert)
+                        .with_context(|| format!("Invalid CA certificate in {}", path.display()))?;
+                }
+            }
+            None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        }
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots);
+        let config = match (&options.cert, &options.key) {
+            (Some(cert), Some(key)) => {
+                let key = PrivateKeyDer::from_pem_file(key)
+                    .with_context(|| format!("Could not read private key {}", key.display()))?;
+                builder
+                    .with_client_auth_cert(load_certs(cert)?, key)
+                    .context("Invalid client certificate")?
+            }
+            (None, None) => builder.with_no_client_auth(),
+            _ => anyhow::bail!("A client certificate needs both a certificate and a key"),
+        };
+
+        let server_name = options
+            .server_name
+            .as_deref()
+            .map(|name| {
+                ServerName::try_from(name.to_string())
+                    .with_context(|| format!("Invalid TLS server name `{}`", name))
+            })
+            .transpose()?;
+        Ok(Transport::Tls {
+            connector: TlsConnector::from(Arc::new(config)),
+            server_name,
+        })
+    }
+
+    pub fn is_tls(&self) -> bool {
+        matches!(self, Transport::Tls { .. })
+    }
+
+    /// Connects to `host:port`, or to the Unix socket at `unix:/path`.
+    pub async fn connect(&self, address: &str) -> Result<FramedStream> {
+        let socket = connect_socket(address)
+            .await
+            .with_context(|| format!("Could not connect to {}", address))?;
+        let stream: Box<dyn Stream> = match self {
+            Transport::Tcp => Box::new(socket),
+            Transport::Tls {
+                connector,
+                server_name,
+            } => {
+                let server_name = match server_name {
+                    Some(name) => name.clone(),
+                    None => host_name(address)?,
+                };
+                let stream = connector
+                    .connect(server_name, socket)
+                    .await
+                    .with_context(|| format!("TLS handshake with {} failed", address))?;
+                Box::new(stream)
+            }
+        };
+
+        let mut codec = LengthDelimitedCodec::new();
+        codec.set_max_frame_length(32 * 1024 * 1024); // 32MB max frame size
+        Ok(Framed::new(stream, codec))
+    }
+}
+
+async fn connect_socket(address: &str) -> Result<Box<dyn Stream>> {
+    match address.strip_prefix(UNIX_PREFIX) {
+        #[cfg(unix)]
+        Some(path) => Ok(Box::new(tokio::net::UnixStream::connect(path).await?)),
+        #[cfg(not(unix))]
+        Some(_) => anyhow::bail!("Unix sockets are not supported on this platform"),
+        None => Ok(Box::new(TcpStream::connect(address).await?)),
+    }
+}
+
+/// A listening socket for either kind of address [`Transport::connect`]
+/// accepts. Used by the mock server.
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener),
+}
+
+impl Listener {
+    /// Binds `host:port` or `unix:/path`. A socket file left behind by an
+    /// earlier server is replaced.
+    pub async fn bind(address: &str) -> Result<Self> {
+        let listener = match address.strip_prefix(UNIX_PREFIX) {
+            #[cfg(unix)]
+            Some(path) => {
+                use std::os::unix::fs::FileTypeExt;
+
+                if std::fs::metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+                    std::fs::remove_file(path)?;
+                }
+                Listener::Unix(tokio::net::UnixListener::bind(path)?)
+            }
+            #[cfg(not(unix))]
+            Some(_) => anyhow::bail!("Unix sockets are not supported on this platform"),
+            None => Listener::Tcp(TcpListener::bind(address).await?),
+        };
+        Ok(listener)
+    }
+
+    /// The address clients connect to, in the form `bind` accepts.
+    pub fn local_addr(&self) -> Result<String> {
+        match self {
+            Listener::Tcp(listener) => Ok(listener.local_addr()?.to_string()),
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                let addr = listener.local_addr()?;
+                let path = addr
+                    .as_pathname()
+                    .ok_or_else(|| anyhow::anyhow!("Unix socket has no path"))?;
+                Ok(format!("{}{}", UNIX_PREFIX, path.display()))
+            }
+        }
+    }
+
+    /// Accepts a connection, returning it with a description of the peer.
+    pub async fn accept(&self) -> Result<(Box<dyn Stream>, String)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, peer) = listener.accept().await?;
+                Ok((Box::new(stream), peer.to_string()))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept().await?;
+                Ok((Box::new(stream), "unix socket".to_string()))
+            }
+        }
+    }
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Could not read certificates from {}", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in {}", path.display());
+    }
+    Ok(certs)
+}
+
+/// Whether the server at `address` runs on this machine and so sees the same
+/// files: a Unix socket or a loopback host.
+pub fn is_local(address: &str) -> bool {
+    if address.starts_with(UNIX_PREFIX) {
+        return true;
+    }
+    let host = host(address);
+    host == "localhost"
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// The host part of `host:port`, as the name to verify the server against.
+fn host_name(address: &str) -> Result<ServerName<'static>> {
+    if address.starts_with(UNIX_PREFIX) {
+        anyhow::bail!("TLS over a Unix socket needs --tls-server-name");
+    }
+    let host = host(address);
+    ServerName::try_from(host.to_string()).with_context(|| {
+        format!(
+            "Cannot verify the server as `{}`, pass --tls-server-name",
+            host
+        )
+    })
+}
+
+/// The host part of `host:port`, without the brackets of an IPv6 address.
+fn host(address: &str) -> &str {
+    address
+        .rsplit_once(':')
+        .map_or(address, |(host, _)| host)
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+}
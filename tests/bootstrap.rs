@@ -0,0 +1,521 @@
+mod support;
+
+use manager_interface::bootstrap;
+use manager_interface::client::{ManagementClient, ServerError};
+use manager_interface::protocol::*;
+use manager_interface::teardown::StartedActors;
+use manager_interface::transport::Transport;
+use serde_json::json;
+use std::collections::HashMap;
+use support::{frame, json_bytes, store_created, ProfileFixture, ScriptedServer};
+
+const PROFILE: &str = r#"
+repl = "manager"
+
+[readiness]
+enabled = false
+
+[stores.runtime]
+create = "on-new-store"
+
+[stores.build]
+
+[actors.content_fs]
+manifest = "content-fs.toml"
+initial_state = { store_id = "${stores.runtime}" }
+readiness = { enabled = true }
+
+[actors.uploader]
+manifest = "uploader.toml"
+initial_state = { runtime_content_fs_address = "${actors.content_fs.id}" }
+when_created = "runtime"
+
+[actors.manager]
+manifest = "manager.toml"
+depends_on = ["content_fs"]
+initial_state = { build_store_id = "${stores.build}", runtime_content_fs_actor_id = "${actors.content_fs.id}" }
+"#;
+
+const MANIFESTS: &[&str] = &["content-fs.toml", "uploader.toml", "manager.toml"];
+
+fn actor_started(id: &str) -> Vec<ManagementResponse> {
+    vec![ManagementResponse::ActorStarted { id: id.to_string() }]
+}
+
+fn actor_stopped(id: &str) -> Vec<ManagementResponse> {
+    vec![ManagementResponse::ActorStopped { id: id.to_string() }]
+}
+
+fn ready(id: &str) -> Vec<ManagementResponse> {
+    vec![ManagementResponse::RequestedMessage {
+        id: id.to_string(),
+        message: json_bytes(json!({ "status": "success" })),
+    }]
+}
+
+/// The frames a bootstrap of `PROFILE` sends up to and including the
+/// `StartActor` of the manager.
+fn bootstrap_frames(fixture: &ProfileFixture) -> Vec<serde_json::Value> {
+    vec![
+        frame(ManagementCommand::NewStore {}),
+        frame(ManagementCommand::NewStore {}),
+        frame(ManagementCommand::StartActor {
+            manifest: fixture.manifest("content-fs.toml"),
+            initial_state: Some(json_bytes(json!({ "store_id": "store-runtime" }))),
+        }),
+        frame(ManagementCommand::RequestActorMessage {
+            id: "actor-fs".to_string(),
+            data: json_bytes(json!({ "action": "get-info", "params": [] })),
+        }),
+        frame(ManagementCommand::StartActor {
+            manifest: fixture.manifest("uploader.toml"),
+            initial_state: Some(json_bytes(
+                json!({ "runtime_content_fs_address": "actor-fs" }),
+            )),
+        }),
+        frame(ManagementCommand::StartActor {
+            manifest: fixture.manifest("manager.toml"),
+            initial_state: Some(json_bytes(json!({
+                "build_store_id": "store-build",
+                "runtime_content_fs_actor_id": "actor-fs",
+            }))),
+        }),
+    ]
+}
+
+#[tokio::test]
+async fn starts_actors_in_dependency_order_with_ids_substituted() {
+    let fixture = ProfileFixture::new(PROFILE, MANIFESTS);
+    let server = ScriptedServer::start(vec![
+        store_created("store-runtime"),
+        store_created("store-build"),
+        actor_started("actor-fs"),
+        ready("actor-fs"),
+        actor_started("actor-uploader"),
+        actor_started("actor-manager"),
+    ])
+    .await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let started = StartedActors::default();
+    let system = bootstrap::run(
+        &mut client,
+        &fixture.profile,
+        &HashMap::new(),
+        true,
+        &started,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(server.received(), bootstrap_frames(&fixture));
+    assert_eq!(system.created_stores, ["runtime", "build"]);
+    assert_eq!(system.stores["build"], "store-build");
+    let actors: Vec<_> = system
+        .actors
+        .iter()
+        .map(|(name, id)| (name.as_str(), id.as_str()))
+        .collect();
+    assert_eq!(
+        actors,
+        [
+            ("content_fs", "actor-fs"),
+            ("uploader", "actor-uploader"),
+            ("manager", "actor-manager"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reuses_given_store_ids_and_skips_actors_of_uncreated_stores() {
+    let fixture = ProfileFixture::new(PROFILE, MANIFESTS);
+    let server = ScriptedServer::start(vec![
+        actor_started("actor-fs"),
+        ready("actor-fs"),
+        actor_started("actor-manager"),
+    ])
+    .await;
+
+    let store_ids = HashMap::from([
+        ("runtime".to_string(), "existing-runtime".to_string()),
+        ("build".to_string(), "existing-build".to_string()),
+    ]);
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let system = bootstrap::run(
+        &mut client,
+        &fixture.profile,
+        &store_ids,
+        false,
+        &StartedActors::default(),
+    )
+    .await
+    .unwrap();
+
+    let received = server.received();
+    assert_eq!(received.len(), 3);
+    assert_eq!(
+        received[0],
+        frame(ManagementCommand::StartActor {
+            manifest: fixture.manifest("content-fs.toml"),
+            initial_state: Some(json_bytes(json!({ "store_id": "existing-runtime" }))),
+        })
+    );
+    assert!(system.created_stores.is_empty());
+    assert!(!system.actors.contains_key("uploader"));
+}
+
+/// Reuses the runtime store, so the uploader and the actors after it are
+/// left out.
+async fn bootstrap_without_uploader(manager: &str) -> anyhow::Result<bootstrap::Bootstrapped> {
+    let profile = format!(
+        r#"
+[readiness]
+enabled = false
+
+[stores.runtime]
+create = "on-new-store"
+
+[actors.uploader]
+manifest = "uploader.toml"
+when_created = "runtime"
+
+[actors.notifier]
+manifest = "notifier.toml"
+initial_state = {{ uploader = "${{actors.uploader.id}}" }}
+
+[actors.manager]
+manifest = "manager.toml"
+{}
+"#,
+        manager
+    );
+    let fixture = ProfileFixture::new(
+        &profile,
+        &["uploader.toml", "notifier.toml", "manager.toml"],
+    );
+    let server = ScriptedServer::start(vec![actor_started("actor-manager")]).await;
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let store_ids = HashMap::from([("runtime".to_string(), "existing-runtime".to_string())]);
+    bootstrap::run(
+        &mut client,
+        &fixture.profile,
+        &store_ids,
+        false,
+        &StartedActors::default(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn skips_actors_that_depend_on_skipped_actors() {
+    let system = bootstrap_without_uploader("").await.unwrap();
+    assert_eq!(system.actors.keys().collect::<Vec<_>>(), ["manager"]);
+
+    let error = bootstrap_without_uploader(r#"depends_on = ["notifier"]"#)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        format!("{:#}", error),
+        "Bootstrap failed: Cannot start REPL actor manager: it depends on notifier, which was skipped"
+    );
+}
+
+#[tokio::test]
+async fn rolls_back_started_actors_in_reverse_order_on_failure() {
+    let fixture = ProfileFixture::new(PROFILE, MANIFESTS);
+    let server = ScriptedServer::start(vec![
+        store_created("store-runtime"),
+        store_created("store-build"),
+        actor_started("actor-fs"),
+        ready("actor-fs"),
+        actor_started("actor-uploader"),
+        vec![ManagementResponse::Error {
+            message: "manifest not found".to_string(),
+        }],
+        actor_stopped("actor-uploader"),
+        actor_stopped("actor-fs"),
+    ])
+    .await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let started = StartedActors::default();
+    let error = bootstrap::run(
+        &mut client,
+        &fixture.profile,
+        &HashMap::new(),
+        true,
+        &started,
+    )
+    .await
+    .unwrap_err();
+
+    let server_error = error.root_cause().downcast_ref::<ServerError>().unwrap();
+    assert_eq!(server_error.message, "manifest not found");
+    assert!(format!("{:#}", error).contains("Could not start manager"));
+
+    let mut expected = bootstrap_frames(&fixture);
+    expected.push(frame(ManagementCommand::StopActor {
+        id: "actor-uploader".to_string(),
+    }));
+    expected.push(frame(ManagementCommand::StopActor {
+        id: "actor-fs".to_string(),
+    }));
+    assert_eq!(server.received(), expected);
+
+    // Nothing is left for a later teardown to stop
+    let report = started.stop_all(&mut client).await;
+    assert!(report.stopped.is_empty() && report.failed.is_empty());
+}
+
+#[tokio::test]
+async fn retries_readiness_probe_until_actor_is_ready() {
+    let profile = r#"
+repl = "echo"
+
+[readiness]
+enabled = true
+backoff_ms = 1
+
+[actors.echo]
+manifest = "echo.toml"
+"#;
+    let fixture = ProfileFixture::new(profile, &["echo.toml"]);
+    let server = ScriptedServer::start(vec![
+        actor_started("actor-echo"),
+        vec![ManagementResponse::RequestedMessage {
+            id: "actor-echo".to_string(),
+            message: json_bytes(json!({ "status": "starting" })),
+        }],
+        ready("actor-echo"),
+    ])
+    .await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    bootstrap::run(
+        &mut client,
+        &fixture.profile,
+        &HashMap::new(),
+        false,
+        &StartedActors::default(),
+    )
+    .await
+    .unwrap();
+
+    let received = server.received();
+    assert_eq!(received.len(), 3);
+    assert_eq!(received[1], received[2]);
+}
+
+#[test]
+fn actor_readiness_overrides_the_profile_field_by_field() {
+    let profile = r#"
+repl = "manager"
+
+[readiness]
+action = "ping"
+retries = 1
+
+[actors.manager]
+manifest = "manager.toml"
+readiness = { retries = 3 }
+
+[actors.uploader]
+manifest = "uploader.toml"
+readiness = { enabled = false }
+"#;
+    let fixture = ProfileFixture::new(profile, &["manager.toml", "uploader.toml"]);
+
+    let manager = fixture.profile.readiness("manager");
+    assert!(manager.enabled);
+    assert_eq!(manager.action, "ping");
+    assert_eq!(manager.retries, 3);
+    assert_eq!(manager.field, "status");
+
+    let uploader = fixture.profile.readiness("uploader");
+    assert!(!uploader.enabled);
+    assert_eq!(uploader.retries, 1);
+}
+
+#[tokio::test]
+async fn discards_the_late_reply_to_an_abandoned_probe() {
+    let reply = |status: &str| ManagementResponse::RequestedMessage {
+        id: "actor-echo".to_string(),
+        message: json_bytes(json!({ "status": status })),
+    };
+    let server = ScriptedServer::start(vec![vec![], vec![reply("late"), reply("fresh")]]).await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let request = json_bytes(json!({ "action": "get-info" }));
+    let timeout = std::time::Duration::from_millis(50);
+    assert!(client
+        .request_actor_message_within("actor-echo", request.clone(), timeout)
+        .await
+        .is_err());
+    let message = client
+        .request_actor_message("actor-echo", request)
+        .await
+        .unwrap();
+    assert_eq!(message, json_bytes(json!({ "status": "fresh" })));
+}
+
+#[tokio::test]
+async fn discards_a_late_error_for_an_abandoned_probe() {
+    let server = ScriptedServer::start(vec![
+        vec![],
+        vec![
+            ManagementResponse::Error {
+                message: "actor-echo is still starting".to_string(),
+            },
+            ManagementResponse::RequestedMessage {
+                id: "actor-echo".to_string(),
+                message: json_bytes(json!({ "status": "success" })),
+            },
+        ],
+    ])
+    .await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let request = json_bytes(json!({ "action": "get-info" }));
+    let timeout = std::time::Duration::from_millis(50);
+    assert!(client
+        .request_actor_message_within("actor-echo", request.clone(), timeout)
+        .await
+        .is_err());
+    let message = client
+        .request_actor_message("actor-echo", request)
+        .await
+        .unwrap();
+    assert_eq!(message, json_bytes(json!({ "status": "success" })));
+}
+
+#[tokio::test]
+async fn forgets_an_abandoned_probe_once_a_later_request_is_answered() {
+    let server = ScriptedServer::start(vec![
+        vec![],
+        store_created("store-1"),
+        vec![ManagementResponse::Error {
+            message: "manifest not found".to_string(),
+        }],
+    ])
+    .await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let request = json_bytes(json!({ "action": "get-info" }));
+    let timeout = std::time::Duration::from_millis(50);
+    assert!(client
+        .request_actor_message_within("actor-echo", request, timeout)
+        .await
+        .is_err());
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+
+    // The probe can no longer be answered, so the error is the start's own
+    let started = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        client.start_actor("/manifests/echo.toml".to_string(), None),
+    )
+    .await
+    .expect("the error was taken for the probe's reply");
+    let error = started.unwrap_err();
+    assert!(
+        error.to_string().contains("manifest not found"),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn queues_frames_that_arrive_before_the_reply() {
+    let status = FrontendMessage::Status {
+        child_running: false,
+        active_operations: vec![],
+    };
+    let server = ScriptedServer::start(vec![vec![
+        support::channel_message("channel-1", "actor-manager", &status),
+        ManagementResponse::StoreCreated {
+            store_id: "store-1".to_string(),
+        },
+    ]])
+    .await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+    match client.next_unsolicited().await.unwrap() {
+        Some(ManagementResponse::ChannelMessage { channel_id, .. }) => {
+            assert_eq!(channel_id, "channel-1")
+        }
+        other => panic!("expected the queued channel message, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn redacts_secrets_in_server_errors() {
+    let server = ScriptedServer::start(vec![vec![ManagementResponse::Error {
+        message: "invalid key sk-test-123".to_string(),
+    }]])
+    .await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    client.redactor().add("sk-test-123");
+    let error = client.new_store().await.unwrap_err();
+    assert_eq!(error.to_string(), "Server error: invalid key ***");
+}
+
+#[tokio::test]
+async fn redacts_secrets_but_not_environment_values_in_initial_state() {
+    std::env::set_var("MANAGER_INTERFACE_TEST_TOKEN", "tok-from-env");
+    std::env::set_var("MANAGER_INTERFACE_TEST_REGION", "eu-west-1");
+    let profile = r#"
+repl = "manager"
+
+[secrets.token]
+source = "env"
+var = "MANAGER_INTERFACE_TEST_TOKEN"
+
+[actors.manager]
+manifest = "manager.toml"
+initial_state = { token = "${secrets.token}", region = "${env.MANAGER_INTERFACE_TEST_REGION}" }
+"#;
+    let fixture = ProfileFixture::new(profile, &["manager.toml"]);
+    let server = ScriptedServer::start(vec![vec![ManagementResponse::Error {
+        message: "bad initial state {\"token\":\"tok-from-env\",\"region\":\"eu-west-1\"}"
+            .to_string(),
+    }]])
+    .await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let error = bootstrap::run(
+        &mut client,
+        &fixture.profile,
+        &HashMap::new(),
+        false,
+        &StartedActors::default(),
+    )
+    .await
+    .unwrap_err();
+    let error = format!("{:#}", error);
+    assert!(!error.contains("tok-from-env"), "{}", error);
+    assert!(error.contains("\"token\":\"***\""), "{}", error);
+    assert!(error.contains("\"region\":\"eu-west-1\""), "{}", error);
+}
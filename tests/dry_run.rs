@@ -0,0 +1,152 @@
+mod support;
+
+use serde_json::{json, Value};
+use support::{json_records, mock_server, ProfileFixture};
+
+const PROFILE: &str = r#"
+repl = "manager"
+
+[secrets.api_key]
+source = "env"
+var = "MANAGER_INTERFACE_TEST_DRY_RUN_KEY"
+
+[stores.build]
+
+[actors.content_fs]
+manifest = "content-fs.toml"
+initial_state = { store_id = "${stores.build}", shard = "${env.MANAGER_INTERFACE_TEST_DRY_RUN_SHARD}" }
+
+[actors.manager]
+manifest = "manager.toml"
+initial_state = { content_fs = "${actors.content_fs.id}", api_key = "${secrets.api_key}" }
+"#;
+
+#[tokio::test]
+async fn prints_the_planned_commands_in_order_with_only_secrets_redacted() {
+    let fixture = ProfileFixture::new(PROFILE, &["content-fs.toml", "manager.toml"]);
+    let output = fixture
+        .command(&mock_server().await)
+        .arg("--dry-run")
+        .env("MANAGER_INTERFACE_TEST_DRY_RUN_KEY", "sk-dry-run-key")
+        // An ordinary value, which must reach the plan unmasked
+        .env("MANAGER_INTERFACE_TEST_DRY_RUN_SHARD", "1")
+        .output()
+        .await
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+
+    let planned: Vec<Value> = json_records(stdout.as_bytes())
+        .into_iter()
+        .filter(|record| record["kind"] == "planned_command")
+        .map(|record| json!([record["sequence"], record["command"]]))
+        .collect();
+    assert_eq!(
+        planned,
+        [
+            json!([1, { "NewStore": {} }]),
+            json!([2, { "StartActor": {
+                "manifest": fixture.manifest("content-fs.toml"),
+                "initial_state": { "store_id": "dry-run-store-1", "shard": "1" },
+            }}]),
+            json!([3, { "RequestActorMessage": {
+                "id": "dry-run-actor-2",
+                "data": { "action": "get-info", "params": [] },
+            }}]),
+            json!([4, { "StartActor": {
+                "manifest": fixture.manifest("manager.toml"),
+                "initial_state": { "content_fs": "dry-run-actor-2", "api_key": "***" },
+            }}]),
+            json!([5, { "RequestActorMessage": {
+                "id": "dry-run-actor-4",
+                "data": { "action": "get-info", "params": [] },
+            }}]),
+        ]
+    );
+    assert!(!stdout.contains("sk-dry-run-key"), "{}", stdout);
+    assert!(!stderr.contains("sk-dry-run-key"), "{}", stderr);
+    assert!(
+        stderr.contains("Dry run complete, nothing was sent"),
+        "{}",
+        stderr
+    );
+}
+
+#[tokio::test]
+async fn plans_without_resolving_secrets() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let marker = dir.path().join("secret-command-ran");
+    let profile = format!(
+        r#"
+[secrets.api_key]
+source = "command"
+command = ["touch", "{}"]
+
+[secrets.token]
+source = "env"
+var = "MANAGER_INTERFACE_TEST_DRY_RUN_UNSET"
+
+[actors.manager]
+manifest = "manager.toml"
+initial_state = {{ api_key = "${{secrets.api_key}}", token = "${{secrets.token}}" }}
+"#,
+        marker.display()
+    );
+    let fixture = ProfileFixture::new(&profile, &["manager.toml"]);
+    let output = fixture
+        .command(&mock_server().await)
+        .arg("--dry-run")
+        .env_remove("MANAGER_INTERFACE_TEST_DRY_RUN_UNSET")
+        .output()
+        .await
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert!(!marker.exists(), "the secret command ran");
+    let started = json_records(&output.stdout)
+        .into_iter()
+        .find(|record| record["kind"] == "planned_command")
+        .unwrap();
+    assert_eq!(
+        started["command"]["StartActor"]["initial_state"],
+        json!({ "api_key": "***", "token": "***" })
+    );
+}
+
+#[tokio::test]
+async fn reports_no_rollback_when_a_plan_fails() {
+    let fixture = ProfileFixture::new(
+        r#"
+[actors.content_fs]
+manifest = "content-fs.toml"
+
+[actors.manager]
+manifest = "manager.toml"
+initial_state = { shard = "${env.MANAGER_INTERFACE_TEST_DRY_RUN_UNSET}", fs = "${actors.content_fs.id}" }
+"#,
+        &["content-fs.toml", "manager.toml"],
+    );
+    let output = fixture
+        .command(&mock_server().await)
+        .arg("--dry-run")
+        .env_remove("MANAGER_INTERFACE_TEST_DRY_RUN_UNSET")
+        .output()
+        .await
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("MANAGER_INTERFACE_TEST_DRY_RUN_UNSET environment variable not set"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("Rolling back"), "{}", stderr);
+    assert!(!stdout.contains("bootstrap_failed"), "{}", stdout);
+}
@@ -0,0 +1,163 @@
+mod support;
+
+use manager_interface::client::ManagementClient;
+use manager_interface::protocol::*;
+use manager_interface::transport::Transport;
+use serde_json::json;
+use support::{store_created, ScriptedServer};
+
+#[tokio::test]
+async fn learns_the_server_protocol_version_on_connect() {
+    let server = ScriptedServer::start(vec![store_created("store-1")]).await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    assert_eq!(
+        client.capabilities().protocol_version,
+        Some(PROTOCOL_VERSION)
+    );
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+
+    assert_eq!(
+        server.handshakes(),
+        [json!({
+            "Handshake": {
+                "protocol_version": PROTOCOL_VERSION,
+                "client": format!("manager-interface {}", env!("CARGO_PKG_VERSION")),
+            }
+        })]
+    );
+}
+
+#[tokio::test]
+async fn treats_a_server_that_rejects_the_handshake_as_legacy() {
+    let server = ScriptedServer::start(vec![store_created("store-1")]).await;
+    server.set_handshake_reply(vec![ManagementResponse::Error {
+        message: "Invalid command: unknown variant `Handshake`".to_string(),
+    }]);
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    assert_eq!(client.capabilities().protocol_version, None);
+    assert_eq!(
+        client.capabilities().to_string(),
+        "legacy protocol, no handshake"
+    );
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+}
+
+#[tokio::test]
+async fn discards_a_handshake_reply_that_arrives_after_the_timeout() {
+    // The server says nothing until the next command, then rejects the
+    // handshake before answering that command
+    let server = ScriptedServer::start(vec![vec![
+        ManagementResponse::Error {
+            message: "Invalid command: unknown variant `Handshake`".to_string(),
+        },
+        ManagementResponse::StoreCreated {
+            store_id: "store-1".to_string(),
+        },
+    ]])
+    .await;
+    server.set_handshake_reply(vec![]);
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    assert_eq!(client.capabilities().protocol_version, None);
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+}
+
+#[tokio::test]
+async fn reconnects_to_a_server_that_hangs_up_on_the_handshake() {
+    let server =
+        ScriptedServer::start(vec![store_created("store-1"), store_created("store-1")]).await;
+    server.hang_up_on_handshakes();
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    assert_eq!(client.capabilities().protocol_version, None);
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+
+    // What the server said is remembered rather than asked again
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    assert_eq!(client.capabilities().protocol_version, None);
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+    assert_eq!(server.handshakes().len(), 1);
+}
+
+#[tokio::test]
+async fn refuses_a_server_that_needs_a_newer_client() {
+    let server = ScriptedServer::start(vec![]).await;
+    server.set_handshake_reply(vec![ManagementResponse::ServerInfo {
+        protocol_version: PROTOCOL_VERSION + 2,
+        min_protocol_version: PROTOCOL_VERSION + 1,
+        commands: vec![],
+    }]);
+
+    let error = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        format!("{:#}", error),
+        format!(
+            "Handshake with {} failed: Server speaks protocol version {} and needs clients \
+             of at least version {}, but this client speaks version {}; upgrade \
+             manager-interface",
+            server.address(),
+            PROTOCOL_VERSION + 2,
+            PROTOCOL_VERSION + 1,
+            PROTOCOL_VERSION
+        )
+    );
+}
+
+#[tokio::test]
+async fn fails_on_a_server_info_that_does_not_decode() {
+    let server = ScriptedServer::start(vec![store_created("store-1")]).await;
+    server.set_raw_handshake_reply(vec![json!({
+        "ServerInfo": { "protocol_version": "one", "commands": [] }
+    })]);
+
+    let error = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .err()
+        .unwrap();
+    let error = format!("{:#}", error);
+    assert!(
+        error.starts_with(&format!("Handshake with {} failed: ", server.address())),
+        "{}",
+        error
+    );
+    // Nothing was sent as if the server were a legacy one
+    assert!(server.received().is_empty());
+}
+
+#[tokio::test]
+async fn refuses_commands_the_server_does_not_support() {
+    let server = ScriptedServer::start(vec![]).await;
+    server.set_handshake_reply(vec![ManagementResponse::ServerInfo {
+        protocol_version: PROTOCOL_VERSION,
+        min_protocol_version: PROTOCOL_VERSION,
+        commands: vec!["StartActor".to_string(), "StopActor".to_string()],
+    }]);
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let error = client.new_store().await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "The server does not support NewStore (protocol version {})",
+            PROTOCOL_VERSION
+        )
+    );
+    assert!(server.received().is_empty());
+}
@@ -0,0 +1,190 @@
+mod support;
+
+use manager_interface::client::ManagementClient;
+use manager_interface::protocol::*;
+use manager_interface::repl::{render_response, ChannelRepl, ReplEvent};
+use manager_interface::transport::Transport;
+use serde_json::json;
+use support::{channel_opened, frame, mock_server, ScriptedServer};
+
+#[tokio::test]
+async fn manages_actors_beyond_starting_them() {
+    let address = mock_server().await;
+    let mut client = ManagementClient::connect(&address, &Transport::Tcp)
+        .await
+        .unwrap();
+
+    let state = json!({ "count": 1 }).to_string().into_bytes();
+    let id = client
+        .start_actor("counter.toml".to_string(), Some(state.clone()))
+        .await
+        .unwrap();
+    assert_eq!(
+        client.list_actors().await.unwrap(),
+        std::slice::from_ref(&id)
+    );
+    assert_eq!(client.get_actor_state(&id).await.unwrap(), Some(state));
+
+    let subscription = client.subscribe_to_actor(&id).await.unwrap();
+    client
+        .send_actor_message(&id, b"increment".to_vec())
+        .await
+        .unwrap();
+    match client.next_unsolicited().await.unwrap() {
+        Some(ManagementResponse::ActorEvent { id: from, event }) => {
+            assert_eq!(from, id);
+            assert_eq!(event["event_type"], "message-received");
+        }
+        other => panic!("expected an actor event, got {:?}", other),
+    }
+    client
+        .unsubscribe_from_actor(&id, &subscription)
+        .await
+        .unwrap();
+
+    client.restart_actor(&id).await.unwrap();
+    let events = client.get_actor_events(&id).await.unwrap();
+    let types: Vec<_> = events.iter().map(|e| e["event_type"].clone()).collect();
+    assert_eq!(
+        types,
+        ["actor-started", "message-received", "actor-restarted"]
+    );
+
+    client.stop_actor(&id).await.unwrap();
+    assert!(client.list_actors().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn repl_sends_management_commands_alongside_the_channel() {
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        vec![ManagementResponse::ActorList {
+            actors: vec!["actor-manager".to_string(), "actor-fs".to_string()],
+        }],
+        vec![ManagementResponse::Error {
+            message: "Actor not found: nope".to_string(),
+        }],
+        vec![ManagementResponse::ActorState {
+            id: "actor-fs".to_string(),
+            state: Some(json!({ "store_id": "store-1" }).to_string().into_bytes()),
+        }],
+    ])
+    .await;
+
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+
+    repl.send_management(ManagementCommand::ListActors {})
+        .await
+        .unwrap();
+    match repl.recv().await {
+        Some(ReplEvent::Management(response)) => assert_eq!(
+            render_response(&response),
+            "Actors (2):\n  actor-manager\n  actor-fs\n"
+        ),
+        other => panic!("expected the actor list, got {:?}", other),
+    }
+
+    // A rejected command is reported without dropping the connection
+    repl.send_management(ManagementCommand::RestartActor {
+        id: "nope".to_string(),
+    })
+    .await
+    .unwrap();
+    match repl.recv().await {
+        Some(ReplEvent::Failed { command, error }) => {
+            assert_eq!(command, "RestartActor");
+            assert_eq!(error, "Actor not found: nope");
+        }
+        other => panic!("expected a failure, got {:?}", other),
+    }
+
+    repl.send_management(ManagementCommand::GetActorState {
+        id: "actor-fs".to_string(),
+    })
+    .await
+    .unwrap();
+    match repl.recv().await {
+        Some(ReplEvent::Management(response)) => assert_eq!(
+            render_response(&response),
+            "State of actor-fs:\n  {\n    \"store_id\": \"store-1\"\n  }\n"
+        ),
+        other => panic!("expected the actor state, got {:?}", other),
+    }
+
+    assert_eq!(
+        server.received()[1..],
+        [
+            frame(ManagementCommand::ListActors {}),
+            frame(ManagementCommand::RestartActor {
+                id: "nope".to_string()
+            }),
+            frame(ManagementCommand::GetActorState {
+                id: "actor-fs".to_string()
+            }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn repl_refuses_commands_a_legacy_server_lacks() {
+    let server = ScriptedServer::start(vec![channel_opened()]).await;
+    server.set_handshake_reply(vec![ManagementResponse::Error {
+        message: "Invalid command".to_string(),
+    }]);
+
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+    repl.send_management(ManagementCommand::ListActors {})
+        .await
+        .unwrap();
+    match repl.recv().await {
+        Some(ReplEvent::Failed { command, error }) => {
+            assert_eq!(command, "ListActors");
+            assert_eq!(
+                error,
+                "not supported by the server (legacy protocol, no handshake)"
+            );
+        }
+        other => panic!("expected a failure, got {:?}", other),
+    }
+    assert_eq!(server.received().len(), 1);
+}
+
+#[test]
+fn renders_actor_events_with_decoded_payloads() {
+    let response = ManagementResponse::ActorEvents {
+        id: "actor-1".to_string(),
+        events: vec![json!({ "event_type": "message-received", "data": b"hi".to_vec() })],
+    };
+    assert_eq!(
+        render_response(&response),
+        "Events of actor-1 (1):\n  {\"data\":\"hi\",\"event_type\":\"message-received\"}\n"
+    );
+}
+
+#[tokio::test]
+async fn fails_on_a_malformed_reply_instead_of_waiting_for_another() {
+    assert!(
+        serde_json::from_value::<ManagementResponse>(json!({ "ActorStarted": { "id": 123 } }))
+            .is_err()
+    );
+    let server = ScriptedServer::start(vec![vec![ManagementResponse::Unknown {
+        variant: "ActorStarted".to_string(),
+        payload: json!({ "id": 123 }),
+    }]])
+    .await;
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    let started = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        client.start_actor("manager.toml".to_string(), None),
+    )
+    .await
+    .expect("the malformed reply was taken for an unsolicited one");
+    assert!(started.is_err());
+}
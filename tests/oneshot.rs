@@ -0,0 +1,202 @@
+mod support;
+
+use manager_interface::oneshot::{execute, run, succeeded};
+use manager_interface::protocol::*;
+use manager_interface::repl::ChannelRepl;
+use manager_interface::transport::Transport;
+use support::{answer, channel_opened, ScriptedServer};
+
+/// Answers the status asked for before a command with `messages`, then a
+/// status with `active` operations running.
+fn status(
+    messages: &[FrontendMessage],
+    active: &[(&str, OperationType)],
+) -> Vec<ManagementResponse> {
+    let mut messages = messages.to_vec();
+    messages.push(FrontendMessage::Status {
+        child_running: true,
+        active_operations: active
+            .iter()
+            .map(|(operation_id, operation_type)| OperationSummary {
+                operation_id: operation_id.to_string(),
+                operation_type: operation_type.clone(),
+                description: "Working".to_string(),
+            })
+            .collect(),
+    });
+    answer(&messages)
+}
+
+fn started(operation_id: &str, operation_type: OperationType) -> FrontendMessage {
+    FrontendMessage::OperationStarted {
+        operation_id: operation_id.to_string(),
+        operation_type,
+        description: "Working".to_string(),
+    }
+}
+
+fn completed(operation_id: &str, success: bool) -> FrontendMessage {
+    FrontendMessage::OperationCompleted {
+        operation_id: operation_id.to_string(),
+        success,
+        message: "Done".to_string(),
+    }
+}
+
+async fn connect(server: &ScriptedServer) -> ChannelRepl {
+    ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn waits_for_the_operation_the_command_started() {
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        status(&[], &[]),
+        answer(&[
+            started("op-1", OperationType::Change),
+            started("op-2", OperationType::Build),
+            completed("op-1", true),
+            FrontendMessage::OperationProgress {
+                operation_id: "op-2".to_string(),
+                description: "Compiling".to_string(),
+                percent_complete: 50.0,
+            },
+            completed("op-2", false),
+        ]),
+    ])
+    .await;
+    let mut repl = connect(&server).await;
+
+    let outcome = execute(&mut repl, FrontendCommand::BuildActor, true)
+        .await
+        .unwrap();
+    match &outcome {
+        FrontendMessage::OperationCompleted { operation_id, .. } => {
+            assert_eq!(operation_id, "op-2")
+        }
+        other => panic!("expected the build to complete, got {:?}", other),
+    }
+    assert!(!succeeded(&outcome));
+}
+
+#[tokio::test]
+async fn status_is_settled_by_the_status_message() {
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        answer(&[
+            FrontendMessage::Log {
+                level: "info".to_string(),
+                message: "Looking".to_string(),
+            },
+            FrontendMessage::Status {
+                child_running: true,
+                active_operations: vec![],
+            },
+        ]),
+    ])
+    .await;
+    let mut repl = connect(&server).await;
+
+    let outcome = execute(&mut repl, FrontendCommand::GetStatus, true)
+        .await
+        .unwrap();
+    assert!(matches!(
+        outcome,
+        FrontendMessage::Status {
+            child_running: true,
+            ..
+        }
+    ));
+    assert!(succeeded(&outcome));
+}
+
+#[tokio::test]
+async fn fails_on_an_error_from_the_actor() {
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        status(&[], &[]),
+        answer(&[FrontendMessage::Error {
+            code: "busy".to_string(),
+            message: "A build is already running".to_string(),
+        }]),
+    ])
+    .await;
+    let mut repl = connect(&server).await;
+
+    let error = execute(&mut repl, FrontendCommand::StartActor, true)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "The actor reported error busy: A build is already running"
+    );
+}
+
+#[tokio::test]
+async fn ignores_operations_and_errors_from_before_the_command() {
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        status(
+            &[FrontendMessage::Error {
+                code: "busy".to_string(),
+                message: "Someone else's change was refused".to_string(),
+            }],
+            &[("op-1", OperationType::Build)],
+        ),
+        answer(&[
+            started("op-1", OperationType::Build),
+            started("op-2", OperationType::Build),
+            FrontendMessage::Error {
+                code: "warning".to_string(),
+                message: "Disk almost full".to_string(),
+            },
+            completed("op-1", false),
+            completed("op-2", true),
+        ]),
+    ])
+    .await;
+    let mut repl = connect(&server).await;
+
+    let outcome = execute(&mut repl, FrontendCommand::BuildActor, true)
+        .await
+        .unwrap();
+    assert_eq!(outcome.operation_id(), Some("op-2"));
+    assert!(succeeded(&outcome));
+}
+
+#[tokio::test]
+async fn closes_the_channel_before_returning() {
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        answer(&[FrontendMessage::Status {
+            child_running: true,
+            active_operations: vec![],
+        }]),
+        vec![ManagementResponse::ChannelClosed {
+            channel_id: "channel-1".to_string(),
+        }],
+    ])
+    .await;
+
+    let succeeded = run(
+        "actor-manager",
+        server.address(),
+        &Transport::Tcp,
+        FrontendCommand::GetStatus,
+        std::time::Duration::from_secs(5),
+        false,
+    )
+    .await
+    .unwrap();
+
+    assert!(succeeded);
+    let received = server.received();
+    assert_eq!(
+        received.last().unwrap(),
+        &support::frame(ManagementCommand::CloseChannel {
+            channel_id: "channel-1".to_string(),
+        })
+    );
+}
@@ -0,0 +1,133 @@
+use manager_interface::operations::{render_operation, render_operations, Operations, Tracker};
+use manager_interface::protocol::*;
+use std::time::Duration;
+
+fn build_event(operation_id: &str, event_type: BuildEventType, message: &str) -> FrontendMessage {
+    FrontendMessage::BuildEvent {
+        operation_id: operation_id.to_string(),
+        event_type,
+        message: message.to_string(),
+        details: BuildEventDetails::default(),
+    }
+}
+
+#[test]
+fn tracks_each_operation_from_start_to_completion() {
+    let mut operations = Operations::default();
+    for msg in [
+        FrontendMessage::Status {
+            child_running: true,
+            active_operations: vec![OperationSummary {
+                operation_id: "op-1".to_string(),
+                operation_type: OperationType::Change,
+                description: "Add a greeting".to_string(),
+            }],
+        },
+        FrontendMessage::OperationStarted {
+            operation_id: "op-2".to_string(),
+            operation_type: OperationType::Build,
+            description: "Building child actor".to_string(),
+        },
+        build_event("op-2", BuildEventType::Log, "Preparing workspace"),
+        FrontendMessage::OperationProgress {
+            operation_id: "op-2".to_string(),
+            description: "Compiling".to_string(),
+            percent_complete: 50.0,
+        },
+        build_event("op-2", BuildEventType::BuildComplete, "Build failed"),
+        FrontendMessage::OperationCompleted {
+            operation_id: "op-2".to_string(),
+            success: false,
+            message: "Build failed".to_string(),
+        },
+        FrontendMessage::Log {
+            level: "info".to_string(),
+            message: "Not about an operation".to_string(),
+        },
+    ] {
+        operations.record(&msg);
+    }
+
+    assert_eq!(
+        operations
+            .iter()
+            .map(|op| op.id.as_str())
+            .collect::<Vec<_>>(),
+        ["op-1", "op-2"]
+    );
+    let change = operations.get("op-1").unwrap();
+    assert_eq!(change.operation_type, Some(OperationType::Change));
+    assert!(!change.is_complete());
+
+    let build = operations.get("op-2").unwrap();
+    assert_eq!(build.operation_type, Some(OperationType::Build));
+    assert_eq!(build.description, "Building child actor");
+    assert_eq!(build.progress, Some((50.0, "Compiling".to_string())));
+    assert_eq!(build.outcome, Some((false, "Build failed".to_string())));
+    assert!(build.ended.is_some_and(|ended| ended >= build.started));
+    assert_eq!(build.build_events.len(), 2);
+
+    let table = render_operations(&operations);
+    assert!(table.contains("op-1  Change  running"), "{}", table);
+    assert!(table.contains("op-2  Build   failed"), "{}", table);
+    let detail = render_operation(build);
+    assert!(detail.contains("Result: ✗ Build failed"), "{}", detail);
+    assert!(detail.contains("Build log:"), "{}", detail);
+    assert!(detail.contains("Preparing workspace"), "{}", detail);
+}
+
+#[tokio::test]
+async fn waits_for_an_operation_to_complete() {
+    let tracker = Tracker::default();
+    tracker.record(&FrontendMessage::OperationStarted {
+        operation_id: "op-1".to_string(),
+        operation_type: OperationType::Build,
+        description: "Building child actor".to_string(),
+    });
+    tracker.record(&FrontendMessage::OperationStarted {
+        operation_id: "other".to_string(),
+        operation_type: OperationType::Change,
+        description: "Something else".to_string(),
+    });
+    assert_eq!(
+        tracker.with(|ops| ops.ids_starting_with("op").collect::<Vec<_>>().join(",")),
+        "op-1"
+    );
+
+    let recorder = tracker.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        recorder.record(&FrontendMessage::OperationCompleted {
+            operation_id: "op-1".to_string(),
+            success: true,
+            message: "Build succeeded".to_string(),
+        });
+    });
+    assert!(
+        tokio::time::timeout(Duration::from_secs(5), tracker.wait("op-1"))
+            .await
+            .unwrap()
+    );
+    assert!(tracker.with(|ops| ops.get("op-1").unwrap().is_complete()));
+    assert!(tracker.with(|ops| !ops.get("other").unwrap().is_complete()));
+}
+
+#[tokio::test]
+async fn stops_waiting_once_the_channel_is_gone() {
+    let tracker = Tracker::default();
+    tracker.record(&FrontendMessage::OperationStarted {
+        operation_id: "op-1".to_string(),
+        operation_type: OperationType::Build,
+        description: "Building child actor".to_string(),
+    });
+
+    let closer = tracker.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        closer.close();
+    });
+    let completed = tokio::time::timeout(Duration::from_secs(5), tracker.wait("op-1"))
+        .await
+        .unwrap();
+    assert!(!completed);
+}
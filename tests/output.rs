@@ -0,0 +1,134 @@
+mod support;
+
+use manager_interface::client::ManagementClient;
+use manager_interface::output::{record, Format};
+use manager_interface::transport::Transport;
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::time::Duration;
+use support::{mock_server, ProfileFixture};
+use tokio::io::AsyncWriteExt;
+
+#[test]
+fn parses_output_formats() {
+    assert_eq!("text".parse::<Format>(), Ok(Format::Text));
+    assert_eq!("json".parse::<Format>(), Ok(Format::Json));
+    assert_eq!("ndjson".parse::<Format>(), Ok(Format::Json));
+    assert_eq!(
+        "yaml".parse::<Format>(),
+        Err("unknown output format `yaml`; use text, json or ndjson".to_string())
+    );
+}
+
+#[test]
+fn records_carry_their_kind_and_a_utc_timestamp() {
+    let mut record = record(
+        "actor_started",
+        json!({ "name": "manager", "id": "actor-1" }),
+    );
+    let timestamp = record["timestamp"].take();
+    let timestamp = timestamp.as_str().unwrap();
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(timestamp).is_ok() && timestamp.ends_with('Z'),
+        "unexpected timestamp {}",
+        timestamp
+    );
+    assert_eq!(
+        record,
+        json!({ "timestamp": null, "kind": "actor_started", "name": "manager", "id": "actor-1" })
+    );
+}
+
+#[tokio::test]
+async fn interactive_repl_writes_only_json_records_to_stdout() {
+    let address = mock_server().await;
+    let mut client = ManagementClient::connect(&address, &Transport::Tcp)
+        .await
+        .unwrap();
+    let id = client
+        .start_actor("manager.toml".to_string(), None)
+        .await
+        .unwrap();
+
+    let mut child = tokio::process::Command::new(env!("CARGO_BIN_EXE_manager-interface"))
+        .args(["--address", &address, "--output", "json", "--interactive"])
+        .args(["attach", "--manager-id", &id])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin
+        .write_all(b"help\nbogus\nshow op-404\nops\nstatus\nexit\n")
+        .await
+        .unwrap();
+    drop(stdin);
+    let output = tokio::time::timeout(Duration::from_secs(30), child.wait_with_output())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.is_empty());
+    for line in stdout.lines() {
+        assert!(
+            serde_json::from_str::<Value>(line).is_ok(),
+            "not a JSON record: {}",
+            line
+        );
+    }
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Available commands:"), "{}", stderr);
+    assert!(stderr.contains("Error: Unknown command"), "{}", stderr);
+    assert!(stderr.contains("Goodbye!"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn masks_secrets_in_the_state_the_repl_shows() {
+    std::env::set_var("MANAGER_INTERFACE_TEST_API_KEY", "sk-live-42");
+    let fixture = ProfileFixture::new(
+        r#"
+repl = "manager"
+
+[readiness]
+enabled = false
+
+[secrets.api_key]
+source = "env"
+var = "MANAGER_INTERFACE_TEST_API_KEY"
+
+[actors.manager]
+manifest = "manager.toml"
+initial_state = { api_key = "${secrets.api_key}" }
+"#,
+        &["manager.toml"],
+    );
+
+    for format in ["text", "json"] {
+        // As a script, so the state is shown before the REPL exits
+        let mut child = fixture
+            .command_with_output(&mock_server().await, format)
+            .stdin(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        stdin
+            .write_all(b"state mock-actor-1\nexit\n")
+            .await
+            .unwrap();
+        drop(stdin);
+        let output = tokio::time::timeout(Duration::from_secs(30), child.wait_with_output())
+            .await
+            .unwrap()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(output.status.success(), "{}", stderr);
+
+        assert!(stdout.contains("***"), "{}", stdout);
+        assert!(!stdout.contains("sk-live-42"), "{}", stdout);
+        assert!(!stderr.contains("sk-live-42"), "{}", stderr);
+    }
+}
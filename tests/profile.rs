@@ -0,0 +1,162 @@
+use manager_interface::profile::Profile;
+use manager_interface::transport;
+use tempfile::TempDir;
+
+/// Loads `contents` as a profile from a fresh directory, returning the error
+/// it is rejected with.
+fn load_error(contents: &str) -> String {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("profile.toml");
+    std::fs::write(&path, contents).unwrap();
+    let error = Profile::load(&path).unwrap_err();
+    format!("{:#}", error)
+}
+
+#[test]
+fn resolves_manifests_next_to_a_bare_relative_profile_path() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("manager-interface.toml"),
+        "[actors.manager]\nmanifest = \"manager.toml\"\n",
+    )
+    .unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let profile = Profile::load("manager-interface.toml".as_ref()).unwrap();
+    assert_eq!(
+        profile.actors["manager"].manifest,
+        dir.path().canonicalize().unwrap().join("manager.toml")
+    );
+}
+
+#[test]
+fn loads_profiles_whose_manifests_only_the_server_has() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("profile.toml");
+    std::fs::write(
+        &path,
+        "[actors.manager]\nmanifest = \"/srv/actors/manager.toml\"\n",
+    )
+    .unwrap();
+
+    let profile = Profile::load(&path).unwrap();
+    assert_eq!(
+        format!("{:#}", profile.check_manifests().unwrap_err()),
+        "Manifest for actor `manager` not found: /srv/actors/manager.toml"
+    );
+}
+
+#[test]
+fn reports_dependency_cycles() {
+    let error = load_error(
+        r#"
+repl = "manager"
+
+[actors.manager]
+manifest = "manager.toml"
+initial_state = { fs = "${actors.content_fs.id}" }
+
+[actors.content_fs]
+manifest = "content-fs.toml"
+depends_on = ["uploader"]
+
+[actors.uploader]
+manifest = "uploader.toml"
+initial_state = { manager = "${actors.manager.id}" }
+"#,
+    );
+    assert!(
+        error.ends_with("Dependency cycle between actors: manager, content_fs, uploader"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn rejects_references_to_undeclared_names() {
+    let cases = [
+        (
+            "repl = \"missing\"\n[actors.manager]\nmanifest = \"m.toml\"\n",
+            "REPL actor `missing` is not declared under [actors]",
+        ),
+        (
+            "[actors.manager]\nmanifest = \"m.toml\"\ndepends_on = [\"helper\"]\n",
+            "Actor `manager` depends on unknown actor `helper`",
+        ),
+        (
+            "[actors.manager]\nmanifest = \"m.toml\"\nwhen_created = \"build\"\n",
+            "Actor `manager` is conditional on unknown store `build`",
+        ),
+        (
+            "[actors.manager]\nmanifest = \"m.toml\"\ninitial_state = { store = \"${stores.build}\" }\n",
+            "Actor `manager` references unknown store `build`",
+        ),
+    ];
+    for (contents, expected) in cases {
+        let error = load_error(contents);
+        assert!(
+            error.starts_with("Invalid profile ") && error.ends_with(expected),
+            "{}",
+            error
+        );
+    }
+}
+
+#[test]
+fn rejects_malformed_placeholders() {
+    let cases = [
+        (
+            "${actors.first.id",
+            "Unterminated placeholder in \"${actors.first.id\"",
+        ),
+        (
+            "${bogus.x}",
+            "Unknown placeholder ${bogus.x}; use stores, actors, secrets or env",
+        ),
+        (
+            "${actors.manager}",
+            "Invalid placeholder ${actors.manager}; expected ${actors.<name>.id}",
+        ),
+        (
+            "${actors.manager.name}",
+            "Invalid placeholder ${actors.manager.name}; expected ${actors.<name>.id}",
+        ),
+        (
+            "${env.}",
+            "Invalid placeholder ${env.}; expected ${env.<VAR>}",
+        ),
+    ];
+    for (placeholder, expected) in cases {
+        let error = load_error(&format!(
+            "[actors.manager]\nmanifest = \"m.toml\"\ninitial_state = {{ value = \"{}\" }}\n",
+            placeholder
+        ));
+        assert!(
+            error.contains("Invalid initial_state for actor `manager`")
+                && error.ends_with(expected),
+            "{}",
+            error
+        );
+    }
+}
+
+#[test]
+fn rejects_unknown_fields() {
+    let error = load_error("[actors.manager]\nmanifest = \"m.toml\"\nmanfest = \"typo\"\n");
+    assert!(error.contains("unknown field `manfest`"), "{}", error);
+}
+
+#[test]
+fn treats_only_unix_sockets_and_loopback_hosts_as_local() {
+    for address in [
+        "127.0.0.1:9000",
+        "localhost:9000",
+        "[::1]:9000",
+        "unix:/tmp/theater.sock",
+    ] {
+        assert!(transport::is_local(address), "{}", address);
+    }
+    for address in ["10.0.0.5:9000", "theater.example.com:9000"] {
+        assert!(!transport::is_local(address), "{}", address);
+    }
+}
@@ -0,0 +1,171 @@
+mod support;
+
+use manager_interface::client::ManagementClient;
+use manager_interface::protocol::*;
+use manager_interface::recording::{self, Direction, Entry};
+use manager_interface::repl::{ChannelRepl, ReplEvent};
+use manager_interface::secrets::Redactor;
+use manager_interface::transport::Transport;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use support::{channel_message, channel_opened, ScriptedServer};
+
+/// The recording every test in this file writes to; there is one per process.
+fn recording() -> &'static Path {
+    static RECORDING: OnceLock<(tempfile::TempDir, PathBuf)> = OnceLock::new();
+    let (_, path) = RECORDING.get_or_init(|| {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        recording::start(&path).unwrap();
+        (dir, path)
+    });
+    path
+}
+
+/// The recorded lines of every connection made to `address`.
+fn recorded_lines(address: &str) -> Vec<String> {
+    let lines: Vec<String> = std::fs::read_to_string(recording())
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect();
+    let entries: Vec<Entry> = lines
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let connections: Vec<u64> = entries
+        .iter()
+        .filter(|entry| entry.address.as_deref() == Some(address))
+        .map(|entry| entry.connection)
+        .collect();
+    lines
+        .into_iter()
+        .zip(entries)
+        .filter(|(_, entry)| connections.contains(&entry.connection))
+        .map(|(line, _)| line)
+        .collect()
+}
+
+#[tokio::test]
+async fn records_every_frame_with_payloads_decoded_and_secrets_redacted() {
+    recording();
+
+    let status = FrontendMessage::Status {
+        child_running: false,
+        active_operations: vec![],
+    };
+    let server = ScriptedServer::start(vec![vec![
+        channel_message("channel-1", "actor-manager", &status),
+        ManagementResponse::ActorStarted {
+            id: "actor-1".to_string(),
+        },
+    ]])
+    .await;
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    client.redactor().add("sk-secret");
+    let initial_state = json!({ "api_key": "sk-secret", "model": "claude" });
+    client
+        .start_actor(
+            "manager.toml".to_string(),
+            Some(serde_json::to_vec(&initial_state).unwrap()),
+        )
+        .await
+        .unwrap();
+    client.next_unsolicited().await.unwrap().unwrap();
+
+    let entries: Vec<Entry> = recorded_lines(server.address())
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let connection = entries[0].connection;
+    assert!(entries.iter().all(|entry| entry.connection == connection));
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| entry.direction)
+            .collect::<Vec<_>>(),
+        [
+            Direction::Connected,
+            Direction::Sent,
+            Direction::Received,
+            Direction::Sent,
+            Direction::Received,
+            Direction::Received,
+        ]
+    );
+    assert_eq!(entries[0].address.as_deref(), Some(server.address()));
+    assert_eq!(
+        entries[3].command,
+        Some(json!({ "StartActor": {
+            "manifest": "manager.toml",
+            "initial_state": { "api_key": "***", "model": "claude" },
+        }}))
+    );
+    assert_eq!(
+        entries[4].response,
+        Some(json!({ "ChannelMessage": {
+            "channel_id": "channel-1",
+            "sender_id": { "Actor": "actor-manager" },
+            "message": { "Status": { "child_running": false, "active_operations": [] } },
+        }}))
+    );
+    assert_eq!(
+        entries[5].response,
+        Some(json!({ "ActorStarted": { "id": "actor-1" } }))
+    );
+}
+
+#[tokio::test]
+async fn redacts_secrets_on_every_connection() {
+    recording();
+    let state = json!({ "api_key": "sk-repl-secret", "turns": 3 });
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        vec![ManagementResponse::ActorState {
+            id: "actor-manager".to_string(),
+            state: Some(serde_json::to_vec(&state).unwrap()),
+        }],
+    ])
+    .await;
+    // Registered by the bootstrap, on a connection of its own
+    Redactor::global().add("sk-repl-secret");
+
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+    repl.send_management(ManagementCommand::GetActorState {
+        id: "actor-manager".to_string(),
+    })
+    .await
+    .unwrap();
+    assert!(matches!(
+        repl.recv().await,
+        Some(ReplEvent::Management(ManagementResponse::ActorState { .. }))
+    ));
+
+    let lines = recorded_lines(server.address());
+    let frame = lines
+        .iter()
+        .map(|line| serde_json::from_str::<Entry>(line).unwrap())
+        .find_map(|entry| {
+            entry
+                .frame
+                .filter(|frame| frame.get("ActorState").is_some())
+        })
+        .unwrap();
+    let Ok(ManagementResponse::ActorState {
+        state: Some(recorded),
+        ..
+    }) = serde_json::from_value(frame)
+    else {
+        panic!("expected the recorded state");
+    };
+    assert_eq!(
+        String::from_utf8(recorded).unwrap(),
+        r#"{"api_key":"***","turns":3}"#
+    );
+    assert!(lines.iter().all(|line| !line.contains("sk-repl-secret")));
+}
@@ -0,0 +1,429 @@
+mod support;
+
+use manager_interface::protocol::*;
+use manager_interface::repl::{render_message, render_response, ChannelRepl, Reconnect, ReplEvent};
+use manager_interface::transport::Transport;
+use serde_json::json;
+use std::time::Duration;
+use support::{channel_message, channel_opened, frame, json_bytes, ScriptedServer};
+
+fn message_sent() -> ManagementResponse {
+    ManagementResponse::MessageSent {
+        channel_id: "channel-1".to_string(),
+    }
+}
+
+async fn next_message(repl: &mut ChannelRepl) -> FrontendMessage {
+    match repl.recv().await {
+        Some(ReplEvent::Message(msg)) => msg,
+        other => panic!("expected a message, got {:?}", other),
+    }
+}
+
+fn quick_reconnect(attempts: u32) -> Reconnect {
+    Reconnect {
+        attempts,
+        backoff: Duration::from_millis(20),
+        max_backoff: Duration::from_millis(40),
+        timeout: Duration::from_secs(5),
+    }
+}
+
+#[tokio::test]
+async fn exchanges_frontend_messages_over_a_channel() {
+    let status = FrontendMessage::Status {
+        child_running: true,
+        active_operations: vec![OperationSummary {
+            operation_id: "op-1".to_string(),
+            operation_type: OperationType::Build,
+            description: "Building".to_string(),
+        }],
+    };
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        vec![
+            message_sent(),
+            channel_message("channel-1", "actor-manager", &status),
+        ],
+        vec![ManagementResponse::ChannelClosed {
+            channel_id: "channel-1".to_string(),
+        }],
+    ])
+    .await;
+
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+    repl.send(FrontendCommand::GetStatus).await.unwrap();
+    let reply = next_message(&mut repl).await;
+    assert_eq!(
+        render_message(&reply),
+        "Status:\n  Child running: true\n  Active operations:\n    - Build (op-1)\n"
+    );
+
+    repl.send(FrontendCommand::Disconnect).await.unwrap();
+    assert!(repl.recv().await.is_none());
+
+    assert_eq!(
+        server.received(),
+        [
+            frame(ManagementCommand::OpenChannel {
+                actor_id: ChannelParticipant::Actor("actor-manager".to_string()),
+                initial_message: json_bytes(json!({ "client_type": "frontend" })),
+            }),
+            frame(ManagementCommand::SendOnChannel {
+                channel_id: "channel-1".to_string(),
+                message: serde_json::to_vec(&FrontendCommand::GetStatus).unwrap(),
+            }),
+            frame(ManagementCommand::CloseChannel {
+                channel_id: "channel-1".to_string(),
+            }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reports_a_channel_the_server_refuses() {
+    let server = ScriptedServer::start(vec![vec![ManagementResponse::Error {
+        message: "Actor not found: actor-manager".to_string(),
+    }]])
+    .await;
+
+    let error = match ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager").await {
+        Ok(_) => panic!("expected opening the channel to fail"),
+        Err(e) => e,
+    };
+    assert_eq!(
+        format!("{:#}", error),
+        "Failed to open channel: Server error: Actor not found: actor-manager"
+    );
+}
+
+#[tokio::test]
+async fn ends_the_session_when_the_server_closes_the_channel() {
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        vec![
+            message_sent(),
+            ManagementResponse::ChannelClosed {
+                channel_id: "channel-1".to_string(),
+            },
+        ],
+    ])
+    .await;
+
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+    repl.send(FrontendCommand::GetStatus).await.unwrap();
+    assert!(repl.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn reports_a_message_the_server_rejects_and_keeps_relaying() {
+    let status = FrontendMessage::Status {
+        child_running: false,
+        active_operations: vec![],
+    };
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        vec![ManagementResponse::Error {
+            message: "Channel is busy".to_string(),
+        }],
+        vec![
+            message_sent(),
+            channel_message("channel-1", "actor-manager", &status),
+        ],
+    ])
+    .await;
+
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+    repl.send(FrontendCommand::GetStatus).await.unwrap();
+    match repl.recv().await {
+        Some(ReplEvent::Failed { command, error }) => {
+            assert_eq!(command, "SendOnChannel");
+            assert_eq!(error, "Channel is busy");
+        }
+        other => panic!("expected the rejection, got {:?}", other),
+    }
+    repl.send(FrontendCommand::GetStatus).await.unwrap();
+    assert!(matches!(
+        next_message(&mut repl).await,
+        FrontendMessage::Status { .. }
+    ));
+    // Still on the first connection: the channel was never reopened
+    assert_eq!(server.received().len(), 3);
+}
+
+#[tokio::test]
+async fn reopens_the_channel_and_sends_held_commands_after_reconnecting() {
+    let status = FrontendMessage::Status {
+        child_running: false,
+        active_operations: vec![],
+    };
+    let started = FrontendMessage::OperationStarted {
+        operation_id: "op-1".to_string(),
+        operation_type: OperationType::Build,
+        description: "Building".to_string(),
+    };
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        vec![
+            message_sent(),
+            channel_message("channel-1", "actor-manager", &status),
+        ],
+        vec![ManagementResponse::ChannelOpened {
+            channel_id: "channel-2".to_string(),
+            actor_id: ChannelParticipant::Actor("actor-manager".to_string()),
+        }],
+        vec![
+            ManagementResponse::MessageSent {
+                channel_id: "channel-2".to_string(),
+            },
+            channel_message("channel-2", "actor-manager", &status),
+        ],
+        vec![
+            ManagementResponse::MessageSent {
+                channel_id: "channel-2".to_string(),
+            },
+            channel_message("channel-2", "actor-manager", &started),
+        ],
+    ])
+    .await;
+
+    let mut repl = ChannelRepl::with_reconnect(
+        server.address(),
+        &Transport::Tcp,
+        "actor-manager",
+        quick_reconnect(3),
+    )
+    .await
+    .unwrap();
+    repl.send(FrontendCommand::GetStatus).await.unwrap();
+    next_message(&mut repl).await;
+
+    server.hang_up();
+    assert!(matches!(
+        repl.recv().await,
+        Some(ReplEvent::ConnectionLost { .. })
+    ));
+    // Typed while the connection is down
+    repl.send(FrontendCommand::BuildActor).await.unwrap();
+    assert!(matches!(
+        repl.recv().await,
+        Some(ReplEvent::Reconnecting { attempt: 1, .. })
+    ));
+    match repl.recv().await {
+        Some(ReplEvent::Reconnected { channel_id }) => assert_eq!(channel_id, "channel-2"),
+        other => panic!("expected to reconnect, got {:?}", other),
+    }
+    assert!(matches!(
+        next_message(&mut repl).await,
+        FrontendMessage::Status { .. }
+    ));
+
+    let open = frame(ManagementCommand::OpenChannel {
+        actor_id: ChannelParticipant::Actor("actor-manager".to_string()),
+        initial_message: json_bytes(json!({ "client_type": "frontend" })),
+    });
+    let send = |channel_id: &str, command: FrontendCommand| {
+        frame(ManagementCommand::SendOnChannel {
+            channel_id: channel_id.to_string(),
+            message: serde_json::to_vec(&command).unwrap(),
+        })
+    };
+    assert!(matches!(
+        next_message(&mut repl).await,
+        FrontendMessage::OperationStarted { .. }
+    ));
+    assert_eq!(
+        server.received(),
+        [
+            open.clone(),
+            send("channel-1", FrontendCommand::GetStatus),
+            open,
+            send("channel-2", FrontendCommand::GetStatus),
+            send("channel-2", FrontendCommand::BuildActor),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn subscribes_again_after_reconnecting() {
+    let subscribed = |subscription_id: &str| {
+        vec![ManagementResponse::Subscribed {
+            id: "actor-child".to_string(),
+            subscription_id: subscription_id.to_string(),
+        }]
+    };
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        subscribed("sub-1"),
+        vec![ManagementResponse::ChannelOpened {
+            channel_id: "channel-2".to_string(),
+            actor_id: ChannelParticipant::Actor("actor-manager".to_string()),
+        }],
+        vec![ManagementResponse::MessageSent {
+            channel_id: "channel-2".to_string(),
+        }],
+        subscribed("sub-2"),
+    ])
+    .await;
+
+    let mut repl = ChannelRepl::with_reconnect(
+        server.address(),
+        &Transport::Tcp,
+        "actor-manager",
+        quick_reconnect(3),
+    )
+    .await
+    .unwrap();
+    repl.send_management(ManagementCommand::SubscribeToActor {
+        id: "actor-child".to_string(),
+    })
+    .await
+    .unwrap();
+    assert!(matches!(
+        repl.recv().await,
+        Some(ReplEvent::Management(ManagementResponse::Subscribed { .. }))
+    ));
+
+    server.hang_up();
+    loop {
+        match repl.recv().await {
+            Some(ReplEvent::Management(ManagementResponse::Subscribed {
+                subscription_id, ..
+            })) => {
+                assert_eq!(subscription_id, "sub-2");
+                break;
+            }
+            Some(_) => continue,
+            None => panic!("the session ended before subscribing again"),
+        }
+    }
+    assert_eq!(
+        server.received().last(),
+        Some(&frame(ManagementCommand::SubscribeToActor {
+            id: "actor-child".to_string(),
+        }))
+    );
+}
+
+#[tokio::test]
+async fn gives_up_after_the_last_reconnection_attempt() {
+    let refused = || {
+        vec![ManagementResponse::Error {
+            message: "Actor not found: actor-manager".to_string(),
+        }]
+    };
+    let server = ScriptedServer::start(vec![channel_opened(), refused(), refused()]).await;
+
+    let mut repl = ChannelRepl::with_reconnect(
+        server.address(),
+        &Transport::Tcp,
+        "actor-manager",
+        quick_reconnect(2),
+    )
+    .await
+    .unwrap();
+    server.hang_up();
+
+    let mut delays = Vec::new();
+    loop {
+        match repl.recv().await {
+            Some(ReplEvent::ConnectionLost { .. }) => {}
+            Some(ReplEvent::Reconnecting { delay, .. }) => delays.push(delay),
+            Some(ReplEvent::GaveUp { attempts, error }) => {
+                assert_eq!(attempts, 2);
+                assert_eq!(
+                    error,
+                    "Failed to open channel: Server error: Actor not found: actor-manager"
+                );
+                break;
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+    assert_eq!(
+        delays,
+        [Duration::from_millis(20), Duration::from_millis(40)]
+    );
+    assert!(repl.recv().await.is_none());
+    assert!(repl.send(FrontendCommand::GetStatus).await.is_err());
+}
+
+#[test]
+fn renders_build_events() {
+    let output = FrontendMessage::BuildEvent {
+        operation_id: "op-2".to_string(),
+        event_type: BuildEventType::CommandOutput,
+        message: "cargo".to_string(),
+        details: BuildEventDetails {
+            stdout: Some("Compiling actor\nFinished".to_string()),
+            stderr: Some("Stderr not available from host function".to_string()),
+            ..Default::default()
+        },
+    };
+    assert_eq!(
+        render_message(&output),
+        "  │ [op-2] Output:\n  │  Compiling actor\n  │  Finished\n"
+    );
+
+    let complete = FrontendMessage::BuildEvent {
+        operation_id: "op-2".to_string(),
+        event_type: BuildEventType::BuildComplete,
+        message: "done".to_string(),
+        details: BuildEventDetails {
+            success: Some(false),
+            error: Some("linker failed".to_string()),
+            ..Default::default()
+        },
+    };
+    assert_eq!(
+        render_message(&complete),
+        "  ✗ [op-2] Build complete: done\n  │  Error: linker failed\n"
+    );
+}
+
+#[tokio::test]
+async fn passes_on_messages_and_responses_it_does_not_recognize() {
+    let message: FrontendMessage =
+        serde_json::from_value(json!({ "DeployStarted": { "target": "staging" } })).unwrap();
+    let response: ManagementResponse =
+        serde_json::from_value(json!({ "ActorMigrated": { "id": "actor-manager" } })).unwrap();
+    assert_eq!(
+        serde_json::to_value(&response).unwrap(),
+        json!({ "ActorMigrated": { "id": "actor-manager" } })
+    );
+    let seen = unknown_variants_seen();
+
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        vec![
+            message_sent(),
+            channel_message("channel-1", "actor-manager", &message),
+            response,
+        ],
+    ])
+    .await;
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+    repl.send(FrontendCommand::GetStatus).await.unwrap();
+
+    let reply = next_message(&mut repl).await;
+    assert_eq!(
+        render_message(&reply),
+        "⚠ Unrecognized DeployStarted message: {\"target\":\"staging\"}\n"
+    );
+    match repl.recv().await {
+        Some(ReplEvent::Management(response)) => assert_eq!(
+            render_response(&response),
+            "⚠ Unrecognized ActorMigrated response: {\"id\":\"actor-manager\"}\n"
+        ),
+        other => panic!("expected a management response, got {:?}", other),
+    }
+    assert!(unknown_variants_seen() >= seen + 2);
+}
@@ -0,0 +1,178 @@
+use manager_interface::protocol::*;
+use manager_interface::replay::{self, Filter, Pace};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+fn received(timestamp: &str, message: Value) -> Value {
+    json!({
+        "timestamp": timestamp,
+        "connection": 2,
+        "direction": "received",
+        "response": { "ChannelMessage": {
+            "channel_id": "channel-1",
+            "sender_id": { "Actor": "actor-manager" },
+            "message": message,
+        }},
+        "frame": { "ChannelMessage": {
+            "channel_id": "channel-1",
+            "sender_id": { "Actor": "actor-manager" },
+            "message": serde_json::to_vec(&message).unwrap(),
+        }},
+    })
+}
+
+fn recording(lines: &[Value]) -> tempfile::NamedTempFile {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    for line in lines {
+        writeln!(file, "{}", line).unwrap();
+    }
+    file
+}
+
+fn session() -> tempfile::NamedTempFile {
+    recording(&[
+        json!({ "timestamp": "2026-01-05T10:00:00.000Z", "connection": 2, "direction": "connected", "address": "127.0.0.1:9000" }),
+        json!({ "timestamp": "2026-01-05T10:00:00.100Z", "connection": 2, "direction": "sent", "command": { "SendOnChannel": { "channel_id": "channel-1", "message": "BuildActor" } }, "frame": { "SendOnChannel": { "channel_id": "channel-1", "message": b"\"BuildActor\"".to_vec() } } }),
+        received(
+            "2026-01-05T10:00:00.200Z",
+            json!({ "OperationStarted": { "operation_id": "op-1", "operation_type": "Build", "description": "Building" } }),
+        ),
+        received(
+            "2026-01-05T10:00:00.300Z",
+            json!({ "Log": { "level": "info", "message": "Compiling" } }),
+        ),
+        received(
+            "2026-01-05T10:00:01.200Z",
+            json!({ "OperationCompleted": { "operation_id": "op-1", "success": true, "message": "Built" } }),
+        ),
+        received(
+            "2026-01-05T10:00:01.300Z",
+            json!({ "OperationStarted": { "operation_id": "op-2", "operation_type": "Change", "description": "Change" } }),
+        ),
+    ])
+}
+
+#[test]
+fn reads_the_frontend_messages_of_a_recording() {
+    let file = session();
+    let (messages, skipped) = replay::messages(&replay::load(file.path()).unwrap()).unwrap();
+    assert_eq!(skipped, 0);
+    let names: Vec<_> = messages.iter().map(|m| m.message.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "OperationStarted",
+            "Log",
+            "OperationCompleted",
+            "OperationStarted"
+        ]
+    );
+}
+
+#[test]
+fn skips_channel_messages_that_are_not_frontend_messages() {
+    let mut text = received("2026-01-05T10:00:00.300Z", json!("plain text"));
+    text["frame"] = json!({ "ChannelMessage": {
+        "channel_id": "channel-1",
+        "sender_id": { "Actor": "actor-manager" },
+        "message": b"plain text".to_vec(),
+    }});
+    let mut binary = received("2026-01-05T10:00:00.200Z", json!([0, 159, 146, 150]));
+    binary["frame"]["ChannelMessage"]["message"] = json!([0, 159, 146, 150]);
+    let file = recording(&[
+        binary,
+        text,
+        received(
+            "2026-01-05T10:00:00.400Z",
+            json!({ "Log": { "level": "info", "message": "Compiling" } }),
+        ),
+    ]);
+
+    let (messages, skipped) = replay::messages(&replay::load(file.path()).unwrap()).unwrap();
+    assert_eq!(skipped, 2);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].message.name(), "Log");
+}
+
+#[test]
+fn reports_where_a_recording_is_invalid() {
+    let file = recording(&[json!({ "timestamp": "2026-01-05T10:00:00.000Z" })]);
+    let error = replay::load(file.path()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("{}:1: invalid entry", file.path().display())
+    );
+}
+
+#[test]
+fn filters_by_operation_and_type() {
+    let started = FrontendMessage::OperationStarted {
+        operation_id: "op-1".to_string(),
+        operation_type: OperationType::Build,
+        description: "Building".to_string(),
+    };
+    let log = FrontendMessage::Log {
+        level: "info".to_string(),
+        message: "Compiling".to_string(),
+    };
+
+    let by_operation = Filter {
+        operation: Some("op-1".to_string()),
+        types: vec![],
+    };
+    assert!(by_operation.matches(&started));
+    assert!(!by_operation.matches(&log));
+
+    let by_type = Filter {
+        operation: None,
+        types: vec!["log".to_string(), "Status".to_string()],
+    };
+    assert!(!by_type.matches(&started));
+    assert!(by_type.matches(&log));
+}
+
+#[test]
+fn rejects_a_recorded_frame_that_is_missing() {
+    let mut entry = received("2026-01-05T10:00:00.200Z", json!("plain text"));
+    entry.as_object_mut().unwrap().remove("frame");
+    let file = recording(&[entry]);
+    let error = replay::load(file.path()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("{}:1: entry has no frame", file.path().display())
+    );
+}
+
+#[tokio::test]
+async fn keeps_the_recorded_gaps_between_the_messages_shown() {
+    let file = session();
+    let entries = replay::load(file.path()).unwrap();
+    let filter = Filter {
+        operation: Some("op-1".to_string()),
+        types: vec![],
+    };
+
+    // One second between the two messages of op-1, played ten times faster
+    let started = Instant::now();
+    let shown = replay::play(
+        replay::messages(&entries).unwrap().0,
+        &filter,
+        Pace::Recorded { speed: 10.0 },
+        true,
+    )
+    .await;
+    assert_eq!(shown, 2);
+    assert!(started.elapsed() >= Duration::from_millis(100));
+
+    let started = Instant::now();
+    let shown = replay::play(
+        replay::messages(&entries).unwrap().0,
+        &Filter::default(),
+        Pace::Instant,
+        true,
+    )
+    .await;
+    assert_eq!(shown, 4);
+    assert!(started.elapsed() < Duration::from_millis(100));
+}
@@ -0,0 +1,217 @@
+mod support;
+
+use manager_interface::client::ManagementClient;
+use manager_interface::mock_server::MockConfig;
+use manager_interface::oneshot::{execute, succeeded};
+use manager_interface::protocol::*;
+use manager_interface::recording::{self, Entry};
+use manager_interface::repl::ChannelRepl;
+use manager_interface::replay;
+use manager_interface::replay_server::{self, Transcript};
+use manager_interface::transport::{Listener, Transport};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use support::{json_records, mock_server, mock_server_with, ProfileFixture};
+
+/// Starts an actor and builds it, returning the actor ID and the message
+/// that settled the build.
+async fn start_and_build(address: &str) -> (String, FrontendMessage) {
+    let mut client = ManagementClient::connect(address, &Transport::Tcp)
+        .await
+        .unwrap();
+    let id = client
+        .start_actor("manager.toml".to_string(), None)
+        .await
+        .unwrap();
+    let mut repl = ChannelRepl::new(address, &Transport::Tcp, &id)
+        .await
+        .unwrap();
+    let outcome = execute(&mut repl, FrontendCommand::BuildActor, false)
+        .await
+        .unwrap();
+    (id, outcome)
+}
+
+#[tokio::test]
+async fn replays_a_recorded_session_to_a_new_client() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.jsonl");
+    recording::start(&path).unwrap();
+
+    let address = mock_server_with(MockConfig {
+        step_delay: Duration::from_millis(1),
+        ..MockConfig::default()
+    })
+    .await;
+    let (recorded_id, recorded_outcome) = start_and_build(&address).await;
+    assert!(succeeded(&recorded_outcome));
+
+    // Other tests in this binary connect while the recording runs
+    let entries = replay::load(&path).unwrap();
+    let ours: Vec<u64> = entries
+        .iter()
+        .filter(|entry| entry.address.as_deref() == Some(address.as_str()))
+        .map(|entry| entry.connection)
+        .collect();
+    let entries: Vec<Entry> = entries
+        .into_iter()
+        .filter(|entry| ours.contains(&entry.connection))
+        .collect();
+    let transcript = Transcript::from_entries(&entries).unwrap();
+    assert_eq!(transcript.connections(), 2);
+    let listener = Listener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(replay_server::serve(listener, transcript, false));
+
+    // A command the recording doesn't expect is refused without losing
+    // the place in the transcript
+    let mut client = ManagementClient::connect(&address, &Transport::Tcp)
+        .await
+        .unwrap();
+    let error = client.list_actors().await.unwrap_err().to_string();
+    assert!(error.contains("Replay expected"), "{}", error);
+    let id = client
+        .start_actor("manager.toml".to_string(), None)
+        .await
+        .unwrap();
+    assert_eq!(id, recorded_id);
+
+    let mut repl = ChannelRepl::new(&address, &Transport::Tcp, &id)
+        .await
+        .unwrap();
+    let outcome = execute(&mut repl, FrontendCommand::BuildActor, false)
+        .await
+        .unwrap();
+    assert!(succeeded(&outcome));
+    assert_eq!(outcome.operation_id(), recorded_outcome.operation_id());
+}
+
+#[tokio::test]
+async fn replays_payloads_byte_for_byte() {
+    let line = |direction: &str, fields: Value| -> Entry {
+        let mut entry = json!({
+            "timestamp": "2026-01-05T10:00:00.000Z",
+            "connection": 1,
+            "direction": direction,
+        });
+        entry
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(entry).unwrap()
+    };
+    let binary = vec![0u8, 159, 146, 150];
+    let event = json!({ "type": "message", "data": b"{\"ok\":true}".to_vec() });
+    let entries = [
+        line("connected", json!({ "address": "127.0.0.1:9000" })),
+        line(
+            "sent",
+            json!({ "command": { "RequestActorMessage": { "id": "actor-1", "data": "ping" } } }),
+        ),
+        line(
+            "received",
+            json!({
+                "response": { "RequestedMessage": { "id": "actor-1", "message": "pong" } },
+                "frame": { "RequestedMessage": { "id": "actor-1", "message": b"pong".to_vec() } },
+            }),
+        ),
+        line(
+            "sent",
+            json!({ "command": { "GetActorState": { "id": "actor-1" } } }),
+        ),
+        line(
+            "received",
+            json!({
+                "response": { "ActorState": { "id": "actor-1", "state": binary } },
+                "frame": { "ActorState": { "id": "actor-1", "state": binary } },
+            }),
+        ),
+        line(
+            "sent",
+            json!({ "command": { "GetActorEvents": { "id": "actor-1" } } }),
+        ),
+        line(
+            "received",
+            json!({
+                "response": { "ActorEvents": { "id": "actor-1", "events": [
+                    { "type": "message", "data": { "ok": true } },
+                ] } },
+                "frame": { "ActorEvents": { "id": "actor-1", "events": [event] } },
+            }),
+        ),
+    ];
+
+    let listener = Listener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let transcript = Transcript::from_entries(&entries).unwrap();
+    tokio::spawn(replay_server::serve(listener, transcript, false));
+
+    // The recorded client had met the server before and sent no handshake
+    let mut client = ManagementClient::connect(&address, &Transport::Tcp)
+        .await
+        .unwrap();
+    assert_eq!(
+        client
+            .request_actor_message("actor-1", b"ping".to_vec())
+            .await
+            .unwrap(),
+        b"pong"
+    );
+    assert_eq!(
+        client.get_actor_state("actor-1").await.unwrap(),
+        Some(binary)
+    );
+    assert_eq!(client.get_actor_events("actor-1").await.unwrap(), [event]);
+}
+
+const PROFILE: &str = r#"
+repl = "manager"
+
+[stores.build]
+
+[actors.manager]
+manifest = "manifests/manager.toml"
+initial_state = { build_store_id = "${stores.build}" }
+"#;
+
+/// Bootstraps `fixture`'s profile on the server at `address`, recording the
+/// session to `record` if given, and returns the IDs it reported as ready.
+async fn bootstrap(address: &str, fixture: &ProfileFixture, record: Option<&Path>) -> Value {
+    let mut command = fixture.command(address);
+    if let Some(record) = record {
+        command.arg("--record").arg(record);
+    }
+    let output = command.output().await.unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    let ready = json_records(&output.stdout)
+        .into_iter()
+        .find(|record| record["kind"] == "ready")
+        .unwrap();
+    json!({ "stores": ready["stores"], "actors": ready["actors"] })
+}
+
+#[tokio::test]
+async fn replays_a_bootstrap_recorded_in_another_checkout() {
+    // Kept in a subdirectory, which the recording must keep
+    let manifests = ["manifests/manager.toml"];
+    let recorded = ProfileFixture::new(PROFILE, &manifests);
+    let path = recorded.dir.path().join("bootstrap.jsonl");
+
+    let address = mock_server().await;
+    let ready = bootstrap(&address, &recorded, Some(&path)).await;
+    let started = replay::load(&path)
+        .unwrap()
+        .into_iter()
+        .find_map(|entry| entry.command?.get("StartActor").cloned())
+        .unwrap();
+    assert_eq!(started["manifest"], "manifests/manager.toml");
+
+    let listener = Listener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let transcript = Transcript::load(&path).unwrap();
+    tokio::spawn(replay_server::serve(listener, transcript, false));
+    let checkout = ProfileFixture::new(PROFILE, &manifests);
+    assert_eq!(bootstrap(&address, &checkout, None).await, ready);
+}
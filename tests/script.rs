@@ -0,0 +1,260 @@
+mod support;
+
+use manager_interface::protocol::*;
+use manager_interface::repl::ChannelRepl;
+use manager_interface::script::Script;
+use manager_interface::transport::Transport;
+use std::process::Stdio;
+use std::time::Duration;
+use support::{answer, channel_opened, frame, mock_server, ProfileFixture, ScriptedServer};
+
+fn operation(
+    operation_id: &str,
+    operation_type: OperationType,
+    success: bool,
+) -> Vec<FrontendMessage> {
+    vec![
+        FrontendMessage::OperationStarted {
+            operation_id: operation_id.to_string(),
+            operation_type,
+            description: "Working".to_string(),
+        },
+        FrontendMessage::OperationCompleted {
+            operation_id: operation_id.to_string(),
+            success,
+            message: format!("{} finished", operation_id),
+        },
+    ]
+}
+
+fn idle() -> FrontendMessage {
+    FrontendMessage::Status {
+        child_running: true,
+        active_operations: vec![],
+    }
+}
+
+fn sent(command: FrontendCommand) -> serde_json::Value {
+    frame(ManagementCommand::SendOnChannel {
+        channel_id: "channel-1".to_string(),
+        message: serde_json::to_vec(&command).unwrap(),
+    })
+}
+
+#[test]
+fn reports_where_a_script_does_not_parse() {
+    let error = Script::parse("deploy.txt", "# set up\nbuild\n\nsleep soon\n").unwrap_err();
+    assert_eq!(
+        format!("{:#}", error),
+        "deploy.txt:4: sleep soon: Usage: sleep <duration>, e.g. 5s or 500ms"
+    );
+}
+
+#[test]
+fn rejects_a_sleep_too_long_to_represent() {
+    let error = Script::parse("deploy.txt", "sleep 99999999999999999999\n").unwrap_err();
+    assert_eq!(
+        format!("{:#}", error),
+        "deploy.txt:1: sleep 99999999999999999999: Duration `99999999999999999999` is out of range"
+    );
+}
+
+/// A change request that completes while a build started by someone else
+/// runs on, finishing with `build_succeeded` once the change is done.
+async fn change_during_a_build(build_succeeded: bool) -> ScriptedServer {
+    let mut messages = vec![FrontendMessage::OperationStarted {
+        operation_id: "op-7".to_string(),
+        operation_type: OperationType::Build,
+        description: "Building".to_string(),
+    }];
+    messages.extend(operation("op-1", OperationType::Change, true));
+    messages.push(FrontendMessage::OperationCompleted {
+        operation_id: "op-7".to_string(),
+        success: build_succeeded,
+        message: "op-7 finished".to_string(),
+    });
+    ScriptedServer::start(vec![channel_opened(), answer(&[idle()]), answer(&messages)]).await
+}
+
+#[tokio::test]
+async fn waits_for_an_operation_by_id_like_the_repl() {
+    let server = change_during_a_build(true).await;
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+
+    let script = Script::parse("deploy.txt", "change add a counter\nwait op-7\nops\n").unwrap();
+    script.run(&mut repl, true).await.unwrap();
+}
+
+#[tokio::test]
+async fn stops_when_an_operation_waited_for_by_id_fails() {
+    let server = change_during_a_build(false).await;
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+
+    let script = Script::parse("deploy.txt", "change add a counter\nwait op-7\nbuild\n").unwrap();
+    let error = script.run(&mut repl, true).await.unwrap_err();
+    assert_eq!(
+        format!("{:#}", error),
+        "deploy.txt:2: wait op-7: operation op-7 failed: op-7 finished"
+    );
+}
+
+#[tokio::test]
+async fn runs_each_command_to_completion_and_stops_at_a_failed_operation() {
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        answer(&[idle()]),
+        answer(&operation("op-1", OperationType::Change, true)),
+        answer(&[idle()]),
+        answer(&operation("op-2", OperationType::Build, false)),
+    ])
+    .await;
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+
+    let script = Script::parse(
+        "deploy.txt",
+        "change add a counter\necho building\nsleep 10ms\nbuild\nstart\n",
+    )
+    .unwrap();
+    let error = script.run(&mut repl, true).await.unwrap_err();
+    assert_eq!(
+        format!("{:#}", error),
+        "deploy.txt:4: build: `build` failed: op-2 finished"
+    );
+
+    // Nothing after the failed operation was sent
+    assert_eq!(
+        server.received()[1..],
+        [
+            sent(FrontendCommand::GetStatus),
+            sent(FrontendCommand::ChangeRequest {
+                description: "add a counter".to_string()
+            }),
+            sent(FrontendCommand::GetStatus),
+            sent(FrontendCommand::BuildActor),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn waits_for_the_operations_active_on_the_manager() {
+    let mut status = vec![
+        FrontendMessage::OperationCompleted {
+            operation_id: "op-0".to_string(),
+            success: false,
+            message: "Finished before the status".to_string(),
+        },
+        FrontendMessage::Status {
+            child_running: false,
+            active_operations: vec![OperationSummary {
+                operation_id: "op-1".to_string(),
+                operation_type: OperationType::Build,
+                description: "Building".to_string(),
+            }],
+        },
+    ];
+    status.extend(
+        operation("op-1", OperationType::Build, true)
+            .into_iter()
+            .skip(1),
+    );
+    let server = ScriptedServer::start(vec![channel_opened(), answer(&status)]).await;
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+
+    let script = Script::parse("deploy.txt", "wait\n").unwrap();
+    script.run(&mut repl, true).await.unwrap();
+    assert_eq!(server.received()[1..], [sent(FrontendCommand::GetStatus)]);
+}
+
+#[tokio::test]
+async fn stops_at_a_rejected_management_command() {
+    let server = ScriptedServer::start(vec![
+        channel_opened(),
+        vec![ManagementResponse::Error {
+            message: "Actor nope not found".to_string(),
+        }],
+    ])
+    .await;
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+
+    let script = Script::parse("deploy.txt", "stop-actor nope\necho stopped\nbuild\n").unwrap();
+    let error = script.run(&mut repl, true).await.unwrap_err();
+    assert_eq!(
+        format!("{:#}", error),
+        "deploy.txt:1: stop-actor nope: `stop-actor nope` failed: Actor nope not found"
+    );
+
+    // Nothing after the rejected command was sent
+    assert_eq!(
+        server.received()[1..],
+        [frame(ManagementCommand::StopActor {
+            id: "nope".to_string()
+        })]
+    );
+}
+
+#[tokio::test]
+async fn stops_when_an_operation_waited_for_fails() {
+    let status = vec![
+        FrontendMessage::Status {
+            child_running: false,
+            active_operations: vec![OperationSummary {
+                operation_id: "op-1".to_string(),
+                operation_type: OperationType::Build,
+                description: "Building".to_string(),
+            }],
+        },
+        FrontendMessage::OperationCompleted {
+            operation_id: "op-1".to_string(),
+            success: false,
+            message: "Build failed".to_string(),
+        },
+    ];
+    let server = ScriptedServer::start(vec![channel_opened(), answer(&status)]).await;
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+
+    let script = Script::parse("deploy.txt", "wait\nbuild\n").unwrap();
+    let error = script.run(&mut repl, true).await.unwrap_err();
+    assert_eq!(
+        format!("{:#}", error),
+        "deploy.txt:1: wait: operation(s) op-1 failed"
+    );
+    assert_eq!(server.received()[1..], [sent(FrontendCommand::GetStatus)]);
+}
+
+#[tokio::test]
+async fn dry_run_leaves_an_open_stdin_alone() {
+    let fixture = ProfileFixture::new(
+        "repl = \"manager\"\n\n[actors.manager]\nmanifest = \"manager.toml\"\n",
+        &["manager.toml"],
+    );
+    let mut child = fixture
+        .command(&mock_server().await)
+        .arg("--dry-run")
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Held open until the dry run is over, like a CI job's pipe
+    let _stdin = child.stdin.take().unwrap();
+
+    let output = tokio::time::timeout(Duration::from_secs(30), child.wait_with_output())
+        .await
+        .expect("the dry run waited for stdin")
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,74 @@
+use manager_interface::secrets::{Redactor, SecretSource};
+
+#[cfg(unix)]
+fn set_mode(path: &std::path::Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap();
+}
+
+#[tokio::test]
+async fn reads_secret_files_relative_to_the_home_directory() {
+    let home = tempfile::tempdir().unwrap();
+    let path = home.path().join("key");
+    std::fs::write(&path, "sk-from-file\n").unwrap();
+    #[cfg(unix)]
+    set_mode(&path, 0o600);
+    std::env::set_var("HOME", home.path());
+
+    let source: SecretSource = "file:~/key".parse().unwrap();
+    assert_eq!(source.resolve().await.unwrap(), "sk-from-file");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn refuses_secret_files_other_users_can_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("key");
+    std::fs::write(&path, "sk-from-file\n").unwrap();
+    set_mode(&path, 0o644);
+
+    let source = SecretSource::File { path: path.clone() };
+    let error = source.resolve().await.unwrap_err().to_string();
+    assert!(error.contains("accessible by other users"), "{}", error);
+    assert!(error.contains("chmod 600"), "{}", error);
+}
+
+#[tokio::test]
+async fn reads_secrets_from_the_environment() {
+    std::env::set_var("MANAGER_INTERFACE_TEST_SECRET", "sk-from-env");
+    let source: SecretSource = "env:MANAGER_INTERFACE_TEST_SECRET".parse().unwrap();
+    assert_eq!(source.resolve().await.unwrap(), "sk-from-env");
+
+    let missing: SecretSource = "env:MANAGER_INTERFACE_TEST_UNSET".parse().unwrap();
+    let error = missing.resolve().await.unwrap_err().to_string();
+    assert_eq!(
+        error,
+        "MANAGER_INTERFACE_TEST_UNSET environment variable not set"
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn reads_secrets_from_the_output_of_a_command() {
+    let source: SecretSource = "command:echo sk-from-command".parse().unwrap();
+    assert_eq!(source.resolve().await.unwrap(), "sk-from-command");
+
+    let failing: SecretSource = "command:false".parse().unwrap();
+    let error = failing.resolve().await.unwrap_err().to_string();
+    assert!(
+        error.starts_with("Secret command `false` failed"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn does_not_mask_values_too_short_to_be_secrets() {
+    let redactor = Redactor::default();
+    redactor.add("1");
+    redactor.add("sk-long-enough");
+    assert_eq!(
+        redactor.redact("dry-run-store-1 with key sk-long-enough"),
+        "dry-run-store-1 with key ***"
+    );
+}
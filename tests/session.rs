@@ -0,0 +1,190 @@
+mod support;
+
+use indexmap::IndexMap;
+use manager_interface::session::Session;
+use serde_json::Value;
+use std::time::Duration;
+use support::{json_records, mock_server, ProfileFixture};
+use tempfile::TempDir;
+
+const PROFILE: &str = r#"
+repl = "manager"
+
+[readiness]
+enabled = false
+
+[stores.build]
+
+[actors.content_fs]
+manifest = "content-fs.toml"
+initial_state = { store_id = "${stores.build}" }
+
+[actors.manager]
+manifest = "manager.toml"
+initial_state = { content_fs = "${actors.content_fs.id}" }
+"#;
+
+fn ids(pairs: &[(&str, &str)]) -> IndexMap<String, String> {
+    pairs
+        .iter()
+        .map(|(name, id)| (name.to_string(), id.to_string()))
+        .collect()
+}
+
+#[test]
+fn round_trips_through_the_session_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("session.json");
+    let session = Session {
+        repl: "manager".to_string(),
+        stores: ids(&[("build", "store-1")]),
+        actors: ids(&[("content_fs", "actor-2"), ("manager", "actor-3")]),
+    };
+    session.save(&path).unwrap();
+
+    let loaded = Session::load(&path).unwrap();
+    assert_eq!(loaded.repl, "manager");
+    assert_eq!(loaded.stores, session.stores);
+    // Declaration order is the start order, which resuming relies on
+    assert_eq!(loaded.actors, session.actors);
+    assert_eq!(loaded.repl_actor_id().unwrap(), "actor-3");
+}
+
+#[test]
+fn has_no_repl_actor_id_once_its_actor_is_forgotten() {
+    let mut session = Session {
+        repl: "manager".to_string(),
+        stores: ids(&[("build", "store-1")]),
+        actors: ids(&[("content_fs", "actor-2"), ("manager", "actor-3")]),
+    };
+    // Only the recorded ID is forgotten, not a namesake started since
+    session.forget_actors(&[
+        ("manager".to_string(), "actor-3".to_string()),
+        ("content_fs".to_string(), "actor-9".to_string()),
+    ]);
+
+    assert_eq!(session.actors, ids(&[("content_fs", "actor-2")]));
+    let error = session.repl_actor_id().unwrap_err();
+    assert!(error.to_string().contains("`manager`"), "{}", error);
+}
+
+#[test]
+fn rejects_a_missing_or_invalid_session_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("session.json");
+    let error = Session::load(&path).unwrap_err();
+    assert!(error.to_string().contains("Could not read session file"));
+
+    std::fs::write(&path, "{\"repl\": \"manager\"}").unwrap();
+    let error = Session::load(&path).unwrap_err();
+    assert!(error.to_string().contains("Invalid session file"));
+}
+
+/// Runs the binary with `args` against the server at `address`, leaving its
+/// actors running, and returns the JSON records it printed.
+async fn run(address: &str, fixture: &ProfileFixture, args: &[&str]) -> Vec<Value> {
+    let output = fixture
+        .command(address)
+        .arg("--keep-running")
+        .args(args)
+        .output();
+    let output = tokio::time::timeout(Duration::from_secs(30), output)
+        .await
+        .unwrap()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    json_records(&output.stdout)
+}
+
+fn records<'a>(records: &'a [Value], kind: &str) -> Vec<&'a Value> {
+    records
+        .iter()
+        .filter(|record| record["kind"] == kind)
+        .collect()
+}
+
+/// A mock server and a profile with a session bootstrapped on it.
+async fn bootstrapped() -> (String, ProfileFixture, Value) {
+    let fixture = ProfileFixture::new(PROFILE, &["content-fs.toml", "manager.toml"]);
+    let address = mock_server().await;
+
+    let first = run(&address, &fixture, &[]).await;
+    let ready = records(&first, "ready")[0].clone();
+    (address, fixture, ready)
+}
+
+#[tokio::test]
+async fn resume_reattaches_to_a_session_whose_actors_are_alive() {
+    let (address, fixture, ready) = bootstrapped().await;
+
+    let resumed = run(&address, &fixture, &["--resume"]).await;
+
+    assert!(
+        records(&resumed, "store_created").is_empty(),
+        "{:?}",
+        resumed
+    );
+    assert!(
+        records(&resumed, "actor_started").is_empty(),
+        "{:?}",
+        resumed
+    );
+    assert!(
+        records(&resumed, "actor_stopped").is_empty(),
+        "{:?}",
+        resumed
+    );
+    let again = records(&resumed, "ready")[0];
+    assert_eq!(again["stores"], ready["stores"]);
+    assert_eq!(again["actors"], ready["actors"]);
+}
+
+#[tokio::test]
+async fn resume_replaces_dead_actors_on_the_previous_stores() {
+    let (address, fixture, ready) = bootstrapped().await;
+    // content_fs is gone from the server, the manager still runs
+    let path = fixture.dir.path().join("session.json");
+    let mut session = Session::load(&path).unwrap();
+    session.actors["content_fs"] = "mock-actor-999".to_string();
+    session.save(&path).unwrap();
+
+    let resumed = run(&address, &fixture, &["--resume"]).await;
+
+    let stopped = records(&resumed, "actor_stopped");
+    assert_eq!(stopped.len(), 1, "{:?}", resumed);
+    assert_eq!(stopped[0]["name"], "manager");
+    assert_eq!(stopped[0]["id"], ready["actors"]["manager"]);
+    assert!(
+        records(&resumed, "store_created").is_empty(),
+        "{:?}",
+        resumed
+    );
+    assert_eq!(records(&resumed, "actor_started").len(), 2, "{:?}", resumed);
+
+    let again = records(&resumed, "ready")[0];
+    assert_eq!(again["stores"], ready["stores"]);
+    assert_ne!(again["actors"]["manager"], ready["actors"]["manager"]);
+    let session = Session::load(&path).unwrap();
+    assert_eq!(
+        session.repl_actor_id().unwrap(),
+        again["actors"]["manager"].as_str().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn resume_without_a_session_file_fails() {
+    let (address, fixture, _) = bootstrapped().await;
+    std::fs::remove_file(fixture.dir.path().join("session.json")).unwrap();
+
+    let output = fixture
+        .command(&address)
+        .args(["--resume", "--keep-running"])
+        .output()
+        .await
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Nothing to resume"), "{}", stderr);
+}
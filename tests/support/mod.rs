@@ -0,0 +1,305 @@
+//! Scripted theater server and fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use manager_interface::mock_server::{self, MockConfig};
+use manager_interface::profile::Profile;
+use manager_interface::protocol::*;
+use manager_interface::transport::{Listener, Stream};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tokio::process::Command;
+use tokio::sync::watch;
+use tokio_rustls::TlsAcceptor;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+/// In-process server that answers the n-th frame it receives, across all
+/// connections, with the n-th entry of its script and records every frame.
+///
+/// Handshakes are answered outside the script, by default as a server that
+/// speaks this client's protocol, and recorded separately.
+pub struct ScriptedServer {
+    address: String,
+    received: Arc<Mutex<Vec<Value>>>,
+    handshakes: Arc<Mutex<Vec<Value>>>,
+    handshake_reply: Arc<Mutex<Vec<Value>>>,
+    hang_up_on_handshake: Arc<AtomicBool>,
+    hang_up: watch::Sender<u32>,
+}
+
+impl ScriptedServer {
+    pub async fn start(script: Vec<Vec<ManagementResponse>>) -> Self {
+        Self::start_with(script, "127.0.0.1:0", None).await
+    }
+
+    /// Like [`ScriptedServer::start`], but connections must speak TLS.
+    pub async fn start_tls(script: Vec<Vec<ManagementResponse>>, acceptor: TlsAcceptor) -> Self {
+        Self::start_with(script, "127.0.0.1:0", Some(acceptor)).await
+    }
+
+    /// Like [`ScriptedServer::start`], but listening on a Unix socket.
+    pub async fn start_unix(script: Vec<Vec<ManagementResponse>>, path: &Path) -> Self {
+        let address = format!("unix:{}", path.display());
+        Self::start_with(script, &address, None).await
+    }
+
+    async fn start_with(
+        script: Vec<Vec<ManagementResponse>>,
+        address: &str,
+        acceptor: Option<TlsAcceptor>,
+    ) -> Self {
+        let listener = Listener::bind(address).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let handshakes = Arc::new(Mutex::new(Vec::new()));
+        let handshake_reply = Arc::new(Mutex::new(vec![serde_json::to_value(
+            ManagementResponse::ServerInfo {
+                protocol_version: PROTOCOL_VERSION,
+                min_protocol_version: PROTOCOL_VERSION,
+                commands: ManagementCommand::NAMES
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect(),
+            },
+        )
+        .unwrap()]));
+        let hang_up_on_handshake = Arc::new(AtomicBool::new(false));
+        let script = Arc::new(Mutex::new(VecDeque::from(script)));
+        let (hang_up, _) = watch::channel(0);
+
+        let frames = received.clone();
+        let greetings = handshakes.clone();
+        let greeting_reply = handshake_reply.clone();
+        let hang_up_on_greeting = hang_up_on_handshake.clone();
+        let hang_ups = hang_up.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let frames = frames.clone();
+                let script = script.clone();
+                let greetings = greetings.clone();
+                let greeting_reply = greeting_reply.clone();
+                let hang_up_on_greeting = hang_up_on_greeting.clone();
+                let mut hang_up = hang_ups.subscribe();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let stream = match acceptor {
+                        Some(acceptor) => match acceptor.accept(stream).await {
+                            Ok(stream) => Box::new(stream) as Box<dyn Stream>,
+                            // The client rejected our certificate or we rejected theirs
+                            Err(_) => return,
+                        },
+                        None => Box::new(stream),
+                    };
+                    let mut framed = Framed::new(stream, LengthDelimitedCodec::new());
+                    loop {
+                        let frame = tokio::select! {
+                            frame = framed.next() => match frame {
+                                Some(Ok(frame)) => frame,
+                                _ => return,
+                            },
+                            _ = hang_up.changed() => return,
+                        };
+                        let frame: Value = serde_json::from_slice(&frame).unwrap();
+                        let replies: Vec<Value> = if frame.get("Handshake").is_some() {
+                            greetings.lock().unwrap().push(frame);
+                            if hang_up_on_greeting.load(Ordering::SeqCst) {
+                                return;
+                            }
+                            greeting_reply.lock().unwrap().clone()
+                        } else {
+                            frames.lock().unwrap().push(frame);
+                            let replies = script.lock().unwrap().pop_front().unwrap_or_default();
+                            replies
+                                .iter()
+                                .map(|r| serde_json::to_value(r).unwrap())
+                                .collect()
+                        };
+                        for reply in replies {
+                            let bytes = serde_json::to_vec(&reply).unwrap();
+                            if framed.send(Bytes::from(bytes)).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        Self {
+            address,
+            received,
+            handshakes,
+            handshake_reply,
+            hang_up_on_handshake,
+            hang_up,
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Answers later handshakes with `replies` instead; none at all makes the
+    /// server ignore them.
+    pub fn set_handshake_reply(&self, replies: Vec<ManagementResponse>) {
+        *self.handshake_reply.lock().unwrap() = replies
+            .iter()
+            .map(|r| serde_json::to_value(r).unwrap())
+            .collect();
+    }
+
+    /// Like [`ScriptedServer::set_handshake_reply`], with replies given as
+    /// raw JSON, which need not be valid responses.
+    pub fn set_raw_handshake_reply(&self, replies: Vec<Value>) {
+        *self.handshake_reply.lock().unwrap() = replies;
+    }
+
+    /// Closes the connection on later handshakes, like a server that drops
+    /// clients sending commands it does not know.
+    pub fn hang_up_on_handshakes(&self) {
+        self.hang_up_on_handshake.store(true, Ordering::SeqCst);
+    }
+
+    /// Every handshake received so far, as JSON.
+    pub fn handshakes(&self) -> Vec<Value> {
+        self.handshakes.lock().unwrap().clone()
+    }
+
+    /// Drops every open connection, as if the network went away. Accepting
+    /// new connections carries on with the rest of the script.
+    pub fn hang_up(&self) {
+        self.hang_up.send_modify(|count| *count += 1);
+    }
+
+    /// Every frame received so far, as JSON.
+    pub fn received(&self) -> Vec<Value> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+/// Starts a mock theater server on a free local port, returning its address.
+pub async fn mock_server() -> String {
+    mock_server_with(MockConfig::default()).await
+}
+
+/// Like [`mock_server`], with `config` instead of the defaults.
+pub async fn mock_server_with(config: MockConfig) -> String {
+    let listener = Listener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(mock_server::serve(listener, config));
+    address
+}
+
+/// The JSON records the binary printed to stdout with `--output json`.
+pub fn json_records(stdout: &[u8]) -> Vec<Value> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+/// The JSON a command is sent as.
+pub fn frame(command: ManagementCommand) -> Value {
+    serde_json::to_value(command).unwrap()
+}
+
+pub fn json_bytes(value: Value) -> Vec<u8> {
+    value.to_string().into_bytes()
+}
+
+pub fn store_created(id: &str) -> Vec<ManagementResponse> {
+    vec![ManagementResponse::StoreCreated {
+        store_id: id.to_string(),
+    }]
+}
+
+/// The reply to opening a channel to the manager actor.
+pub fn channel_opened() -> Vec<ManagementResponse> {
+    vec![ManagementResponse::ChannelOpened {
+        channel_id: "channel-1".to_string(),
+        actor_id: ChannelParticipant::Actor("actor-manager".to_string()),
+    }]
+}
+
+/// Answers the command sent on the channel with `messages` from the manager.
+pub fn answer(messages: &[FrontendMessage]) -> Vec<ManagementResponse> {
+    let mut replies = vec![ManagementResponse::MessageSent {
+        channel_id: "channel-1".to_string(),
+    }];
+    replies.extend(
+        messages
+            .iter()
+            .map(|msg| channel_message("channel-1", "actor-manager", msg)),
+    );
+    replies
+}
+
+pub fn channel_message(
+    channel_id: &str,
+    actor_id: &str,
+    message: &FrontendMessage,
+) -> ManagementResponse {
+    ManagementResponse::ChannelMessage {
+        channel_id: channel_id.to_string(),
+        sender_id: ChannelParticipant::Actor(actor_id.to_string()),
+        message: serde_json::to_vec(message).unwrap(),
+    }
+}
+
+/// A profile written to a temporary directory, with an empty manifest file
+/// for every `manifests` entry.
+pub struct ProfileFixture {
+    pub dir: TempDir,
+    pub profile: Profile,
+}
+
+impl ProfileFixture {
+    pub fn new(contents: &str, manifests: &[&str]) -> Self {
+        let dir = TempDir::new().unwrap();
+        for manifest in manifests {
+            let path = dir.path().join(manifest);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let path = dir.path().join("profile.toml");
+        std::fs::write(&path, contents).unwrap();
+        let profile = Profile::load(&path).unwrap();
+        Self { dir, profile }
+    }
+
+    /// The binary set up to run this profile against the server at
+    /// `address`, with JSON records on stdout and its session file next to
+    /// the profile. Stdin is closed and the output captured unless the
+    /// caller says otherwise.
+    pub fn command(&self, address: &str) -> Command {
+        self.command_with_output(address, "json")
+    }
+
+    /// Like [`ProfileFixture::command`], with records in `format` instead.
+    pub fn command_with_output(&self, address: &str, format: &str) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_manager-interface"));
+        command
+            .args(["--address", address, "--output", format])
+            .arg("--profile")
+            .arg(self.dir.path().join("profile.toml"))
+            .arg("--session-file")
+            .arg(self.dir.path().join("session.json"))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        command
+    }
+
+    /// Absolute manifest path as sent in `StartActor`.
+    pub fn manifest(&self, name: &str) -> String {
+        let path: PathBuf = self.dir.path().canonicalize().unwrap().join(name);
+        path.to_string_lossy().into_owned()
+    }
+}
@@ -0,0 +1,161 @@
+mod support;
+
+use manager_interface::session::Session;
+use serde_json::Value;
+use std::process::Stdio;
+use std::time::Duration;
+use support::{json_records, mock_server, ProfileFixture};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Child;
+
+const PROFILE: &str = r#"
+repl = "manager"
+
+[readiness]
+enabled = false
+
+[stores.build]
+
+[actors.manager]
+manifest = "manager.toml"
+initial_state = { build_store_id = "${stores.build}" }
+"#;
+
+/// Starts the binary against a fresh mock server with JSON records on
+/// stdout and `input` written to stdin, which is closed unless `interactive`.
+async fn spawn(fixture: &ProfileFixture, input: &str, interactive: bool) -> Child {
+    let address = mock_server().await;
+    let mut command = fixture.command(&address);
+    if interactive {
+        command.arg("--interactive");
+    }
+    let mut child = command.stdin(Stdio::piped()).spawn().unwrap();
+    let stdin = child.stdin.as_mut().unwrap();
+    stdin.write_all(input.as_bytes()).await.unwrap();
+    if !interactive {
+        drop(child.stdin.take());
+    }
+    child
+}
+
+/// Reads JSON records from stdout until one of `kind`, returning them all.
+async fn read_until(
+    lines: &mut tokio::io::Lines<BufReader<tokio::process::ChildStdout>>,
+    kind: &str,
+) -> Vec<Value> {
+    let mut records = Vec::new();
+    while let Some(line) = lines.next_line().await.unwrap() {
+        let record: Value = serde_json::from_str(&line).unwrap();
+        let done = record["kind"] == kind;
+        records.push(record);
+        if done {
+            return records;
+        }
+    }
+    panic!("stdout closed before a {} record: {:?}", kind, records);
+}
+
+fn kinds(records: &[Value]) -> Vec<&str> {
+    records
+        .iter()
+        .map(|record| record["kind"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn stops_started_actors_and_forgets_them_on_exit() {
+    let fixture = ProfileFixture::new(PROFILE, &["manager.toml"]);
+    let child = spawn(&fixture, "status\n", false).await;
+    let output = tokio::time::timeout(Duration::from_secs(30), child.wait_with_output())
+        .await
+        .unwrap()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    let records = json_records(&output.stdout);
+    assert_eq!(
+        kinds(&records).last(),
+        Some(&"actor_stopped"),
+        "{:?}",
+        records
+    );
+
+    // The store can be resumed, the stopped actor cannot
+    let session = Session::load(&fixture.dir.path().join("session.json")).unwrap();
+    assert_eq!(session.stores.len(), 1);
+    assert!(session.actors.is_empty(), "{:?}", session.actors);
+}
+
+#[cfg(unix)]
+async fn terminate(child: &Child) {
+    let pid = child.id().unwrap().to_string();
+    let killed = std::process::Command::new("kill")
+        .args(["-TERM", &pid])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn stops_started_actors_on_sigterm_and_exits_with_its_status() {
+    let fixture = ProfileFixture::new(PROFILE, &["manager.toml"]);
+    let mut child = spawn(&fixture, "sleep 30s\nbuild\n", false).await;
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    read_until(&mut lines, "ready").await;
+
+    terminate(&child).await;
+
+    let records = tokio::time::timeout(
+        Duration::from_secs(30),
+        read_until(&mut lines, "actor_stopped"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(records.last().unwrap()["name"], "manager");
+    let status = tokio::time::timeout(Duration::from_secs(30), child.wait())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.code(), Some(143));
+
+    let session = Session::load(&fixture.dir.path().join("session.json")).unwrap();
+    assert!(session.actors.is_empty(), "{:?}", session.actors);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn ends_a_waiting_prompt_on_sigterm() {
+    let fixture = ProfileFixture::new(PROFILE, &["manager.toml"]);
+    // Stdin stays open, so the prompt waits for a line that never comes
+    let mut child = spawn(&fixture, "", true).await;
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    read_until(&mut lines, "ready").await;
+    // The REPL greets on stderr right before it prompts. The rest of
+    // stderr stays unread, but the pipe is kept open for it
+    let mut progress = BufReader::new(child.stderr.take().unwrap()).lines();
+    let greeting = async {
+        while let Some(line) = progress.next_line().await.unwrap() {
+            if line.contains("Type 'help' for available commands") {
+                return;
+            }
+        }
+        panic!("stderr closed before the REPL prompted");
+    };
+    tokio::time::timeout(Duration::from_secs(30), greeting)
+        .await
+        .unwrap();
+
+    terminate(&child).await;
+    tokio::time::timeout(
+        Duration::from_secs(30),
+        read_until(&mut lines, "actor_stopped"),
+    )
+    .await
+    .unwrap();
+    let status = tokio::time::timeout(Duration::from_secs(30), child.wait())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.code(), Some(143));
+}
@@ -0,0 +1,196 @@
+mod support;
+
+use manager_interface::client::ManagementClient;
+use manager_interface::protocol::*;
+use manager_interface::repl::ChannelRepl;
+use manager_interface::transport::{TlsOptions, Transport};
+use rcgen::{CertifiedKey, KeyPair};
+use std::path::PathBuf;
+use std::sync::Arc;
+use support::{channel_opened, frame, store_created, ScriptedServer};
+use tempfile::TempDir;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{self, RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+/// A self-signed certificate and its key, also written out as PEM files.
+struct Identity {
+    cert: CertifiedKey<KeyPair>,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl Identity {
+    fn new(dir: &TempDir, name: &str) -> Self {
+        let cert = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+        let cert_path = dir.path().join(format!("{}.pem", name));
+        let key_path = dir.path().join(format!("{}.key", name));
+        std::fs::write(&cert_path, cert.cert.pem()).unwrap();
+        std::fs::write(&key_path, cert.signing_key.serialize_pem()).unwrap();
+        Self {
+            cert,
+            cert_path,
+            key_path,
+        }
+    }
+
+    fn der(&self) -> CertificateDer<'static> {
+        self.cert.cert.der().clone()
+    }
+
+    fn key_der(&self) -> PrivateKeyDer<'static> {
+        PrivateKeyDer::Pkcs8(self.cert.signing_key.serialize_der().into())
+    }
+}
+
+fn acceptor(server: &Identity, client_ca: Option<&Identity>) -> TlsAcceptor {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .unwrap();
+    let builder = match client_ca {
+        Some(ca) => {
+            let mut roots = RootCertStore::empty();
+            roots.add(ca.der()).unwrap();
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .unwrap();
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder
+        .with_single_cert(vec![server.der()], server.key_der())
+        .unwrap();
+    TlsAcceptor::from(Arc::new(config))
+}
+
+#[tokio::test]
+async fn bootstrap_and_repl_connections_use_tls() {
+    let dir = TempDir::new().unwrap();
+    let server_id = Identity::new(&dir, "localhost");
+    let server = ScriptedServer::start_tls(
+        vec![store_created("store-1"), channel_opened()],
+        acceptor(&server_id, None),
+    )
+    .await;
+
+    let transport = Transport::tls(&TlsOptions {
+        ca: Some(server_id.cert_path.clone()),
+        server_name: Some("localhost".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let mut client = ManagementClient::connect(server.address(), &transport)
+        .await
+        .unwrap();
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+    ChannelRepl::new(server.address(), &transport, "actor-manager")
+        .await
+        .unwrap();
+
+    let received = server.received();
+    assert_eq!(received[0], frame(ManagementCommand::NewStore {}));
+    assert_eq!(received.len(), 2);
+}
+
+#[tokio::test]
+async fn verifies_the_server_against_the_host_in_the_address() {
+    let dir = TempDir::new().unwrap();
+    let server_id = Identity::new(&dir, "localhost");
+    let server =
+        ScriptedServer::start_tls(vec![store_created("store-1")], acceptor(&server_id, None)).await;
+    let port = server.address().rsplit_once(':').unwrap().1;
+
+    let transport = Transport::tls(&TlsOptions {
+        ca: Some(server_id.cert_path.clone()),
+        ..Default::default()
+    })
+    .unwrap();
+
+    // Connecting by IP fails as the certificate is only valid for localhost
+    let error = ManagementClient::connect(server.address(), &transport)
+        .await
+        .err()
+        .unwrap();
+    assert!(format!("{:#}", error).contains("TLS handshake"));
+
+    let mut client = ManagementClient::connect(&format!("localhost:{}", port), &transport)
+        .await
+        .unwrap();
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+}
+
+#[tokio::test]
+async fn rejects_a_server_that_is_not_trusted() {
+    let dir = TempDir::new().unwrap();
+    let server_id = Identity::new(&dir, "localhost");
+    let server = ScriptedServer::start_tls(vec![], acceptor(&server_id, None)).await;
+
+    // Without --tls-ca only the built-in roots are trusted
+    let transport = Transport::tls(&TlsOptions {
+        server_name: Some("localhost".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    let error = ManagementClient::connect(server.address(), &transport)
+        .await
+        .err()
+        .unwrap();
+    assert!(format!("{:#}", error).contains("UnknownIssuer"));
+}
+
+#[tokio::test]
+async fn presents_a_client_certificate() {
+    let dir = TempDir::new().unwrap();
+    let server_id = Identity::new(&dir, "localhost");
+    let client_id = Identity::new(&dir, "client");
+    let server = ScriptedServer::start_tls(
+        vec![store_created("store-1")],
+        acceptor(&server_id, Some(&client_id)),
+    )
+    .await;
+
+    let options = TlsOptions {
+        ca: Some(server_id.cert_path.clone()),
+        server_name: Some("localhost".to_string()),
+        ..Default::default()
+    };
+
+    // The server only notices the missing certificate after the handshake
+    let anonymous = Transport::tls(&options).unwrap();
+    let refused = match ManagementClient::connect(server.address(), &anonymous).await {
+        Ok(mut client) => client.new_store().await.is_err(),
+        Err(_) => true,
+    };
+    assert!(refused);
+
+    let transport = Transport::tls(&TlsOptions {
+        cert: Some(client_id.cert_path.clone()),
+        key: Some(client_id.key_path.clone()),
+        ..options
+    })
+    .unwrap();
+    let mut client = ManagementClient::connect(server.address(), &transport)
+        .await
+        .unwrap();
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+}
+
+#[test]
+fn requires_both_halves_of_a_client_certificate() {
+    let dir = TempDir::new().unwrap();
+    let client_id = Identity::new(&dir, "client");
+    let error = Transport::tls(&TlsOptions {
+        cert: Some(client_id.cert_path.clone()),
+        ..Default::default()
+    })
+    .err()
+    .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "A client certificate needs both a certificate and a key"
+    );
+}
@@ -0,0 +1,100 @@
+#![cfg(unix)]
+
+mod support;
+
+use manager_interface::client::ManagementClient;
+use manager_interface::mock_server::{self, MockConfig};
+use manager_interface::protocol::*;
+use manager_interface::repl::{ChannelRepl, ReplEvent};
+use manager_interface::transport::{Listener, Transport};
+use std::time::Duration;
+use support::{answer, channel_opened, frame, store_created, ScriptedServer};
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn bootstrap_and_repl_connect_over_a_unix_socket() {
+    let dir = TempDir::new().unwrap();
+    let status = FrontendMessage::Status {
+        child_running: false,
+        active_operations: vec![],
+    };
+    let server = ScriptedServer::start_unix(
+        vec![
+            store_created("store-1"),
+            channel_opened(),
+            answer(&[status]),
+        ],
+        &dir.path().join("theater.sock"),
+    )
+    .await;
+    assert!(server.address().starts_with("unix:/"));
+
+    let mut client = ManagementClient::connect(server.address(), &Transport::Tcp)
+        .await
+        .unwrap();
+    assert_eq!(client.new_store().await.unwrap(), "store-1");
+
+    let mut repl = ChannelRepl::new(server.address(), &Transport::Tcp, "actor-manager")
+        .await
+        .unwrap();
+    let status = repl.ping(Duration::from_secs(5)).await.unwrap();
+    assert!(matches!(status, FrontendMessage::Status { .. }));
+    assert_eq!(server.received()[0], frame(ManagementCommand::NewStore {}));
+}
+
+#[tokio::test]
+async fn mock_server_listens_on_a_unix_socket() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("mock.sock");
+    // A socket left behind by an earlier run is replaced
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+    let listener = Listener::bind(&format!("unix:{}", path.display()))
+        .await
+        .unwrap();
+    let address = listener.local_addr().unwrap();
+    let config = MockConfig {
+        step_delay: Duration::from_millis(1),
+        ..Default::default()
+    };
+    tokio::spawn(mock_server::serve(listener, config));
+
+    let mut client = ManagementClient::connect(&address, &Transport::Tcp)
+        .await
+        .unwrap();
+    let manifest = dir.path().join("manager.toml");
+    let actor_id = client
+        .start_actor(manifest.to_string_lossy().into_owned(), None)
+        .await
+        .unwrap();
+
+    let mut repl = ChannelRepl::new(&address, &Transport::Tcp, &actor_id)
+        .await
+        .unwrap();
+    repl.send(FrontendCommand::StartActor).await.unwrap();
+    loop {
+        match repl.recv().await {
+            Some(ReplEvent::Message(FrontendMessage::OperationCompleted { success, .. })) => {
+                assert!(success);
+                break;
+            }
+            Some(_) => {}
+            None => panic!("channel closed before the operation completed"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn tls_over_a_unix_socket_needs_a_server_name() {
+    let dir = TempDir::new().unwrap();
+    let server = ScriptedServer::start_unix(vec![], &dir.path().join("theater.sock")).await;
+    let transport = Transport::tls(&Default::default()).unwrap();
+    let error = ManagementClient::connect(server.address(), &transport)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "TLS over a Unix socket needs --tls-server-name"
+    );
+}